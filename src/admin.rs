@@ -0,0 +1,285 @@
+use std::sync::Arc;
+use std::time::Duration;
+use eframe::egui::{self, Context, DragValue, Key, KeyboardShortcut, Modifiers, RichText, Slider, TextEdit};
+use tracing::{error, info, warn};
+use crate::rounds::{today_stats, RoundStats};
+use crate::settings::{ModerationPolicy, Settings};
+use crate::{force_reset, stats, ApplicationState, InterTaskMessageToBot, LLMModel};
+
+/// Opens and closes the admin panel
+const ADMIN_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::CTRL.plus(Modifiers::SHIFT), Key::A);
+
+/// State of the hidden operator panel and what it shows about the booth
+#[derive(Debug, Default)]
+pub struct AdminPanel {
+    open: bool,
+    unlocked: bool,
+    pin_input: String,
+    /// Copy of the settings being edited, applied with "Apply"
+    draft: Option<Settings>,
+    stats: RoundStats,
+    /// Who we're connected with, if anyone
+    pub peer: Option<String>,
+    pub last_connection_error: Option<String>,
+    pub last_llm_latency: Option<Duration>,
+    pub last_llm_error: Option<String>,
+    /// Failed LLM requests since the start
+    pub llm_errors: u32,
+}
+
+impl AdminPanel {
+    /// Remember the outcome of an LLM request
+    pub fn record_llm_status(&mut self, latency: Option<Duration>, error: Option<String>) {
+        if latency.is_some() {
+            self.last_llm_latency = latency;
+        }
+        if error.is_some() {
+            self.llm_errors += 1;
+            self.last_llm_error = error;
+        }
+    }
+
+    fn close(&mut self) {
+        self.open = false;
+        self.unlocked = false;
+        self.pin_input.clear();
+        self.draft = None;
+    }
+}
+
+/// Toggle the panel with Ctrl+Shift+A and show it on top of the current screen.
+/// The panel is disabled if no `admin_pin` is configured.
+pub fn render_admin_panel(app: &mut ApplicationState, ctx: &Context) {
+    if app.settings.admin_pin.is_empty() {
+        return;
+    }
+
+    if ctx.input_mut(|i| i.consume_shortcut(&ADMIN_SHORTCUT)) {
+        if app.admin.open {
+            app.admin.close();
+        } else {
+            app.admin.open = true;
+        }
+    }
+
+    if !app.admin.open {
+        return;
+    }
+
+    let mut open = true;
+    egui::Window::new("Admin").open(&mut open).collapsible(false).resizable(true).default_width(500.0).show(ctx, |ui| {
+        if !app.admin.unlocked {
+            render_pin_prompt(app, ui);
+        } else {
+            render_status(app, ui);
+            ui.separator();
+            render_settings_editor(app, ui);
+        }
+    });
+
+    if !open {
+        app.admin.close();
+    }
+}
+
+fn render_pin_prompt(app: &mut ApplicationState, ui: &mut egui::Ui) {
+    ui.horizontal(|ui| {
+        ui.label("PIN");
+        let response = ui.add(TextEdit::singleline(&mut app.admin.pin_input).password(true));
+        let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+        if ui.button("Unlock").clicked() || submitted {
+            if app.admin.pin_input == app.settings.admin_pin.expose() {
+                app.admin.unlocked = true;
+                app.admin.stats = today_stats(&app.settings.rounds_file);
+            } else {
+                warn!("Wrong admin PIN entered");
+            }
+            app.admin.pin_input.clear();
+        }
+    });
+}
+
+fn render_status(app: &mut ApplicationState, ui: &mut egui::Ui) {
+    ui.label(RichText::new("Connection").strong());
+    match &app.admin.peer {
+        Some(peer) => ui.label(format!("Connected with {}", peer)),
+        None => ui.label("Not connected"),
+    };
+    if let Some(error) = &app.admin.last_connection_error {
+        ui.label(format!("Last error: {}", error));
+    }
+    ui.add_space(5.0);
+
+    ui.label(RichText::new("LLM").strong());
+    match app.admin.last_llm_latency {
+        Some(latency) => ui.label(format!("Last latency: {} ms", latency.as_millis())),
+        None => ui.label("No request yet"),
+    };
+    ui.label(format!("Errors: {}", app.admin.llm_errors));
+    if let Some(error) = &app.admin.last_llm_error {
+        ui.label(format!("Last error: {}", error));
+    }
+    ui.add_space(5.0);
+
+    ui.horizontal(|ui| {
+        ui.label(RichText::new("Today").strong());
+        if ui.small_button("Refresh").clicked() {
+            app.admin.stats = today_stats(&app.settings.rounds_file);
+        }
+    });
+    let stats = &app.admin.stats;
+    ui.label(format!("Rounds: {}, guesses: {}, AI identified: {}", stats.rounds, stats.guessed, stats.correct));
+    if let Some(rate) = stats.detection_rate() {
+        ui.label(format!("Detection rate: {:.0} %", rate * 100.0));
+    }
+    if let Some(confidence) = stats.average_confidence() {
+        ui.label(format!("Average confidence: {:.0} % (correct: {}, wrong: {})",
+            confidence,
+            stats.average_confidence_correct().map_or("-".to_string(), |c| format!("{:.0} %", c)),
+            stats.average_confidence_wrong().map_or("-".to_string(), |c| format!("{:.0} %", c)),
+        ));
+        ui.label(format!("Guesses with reasoning: {}", stats.reasonings));
+    }
+    ui.add_space(5.0);
+
+    ui.horizontal(|ui| {
+        if ui.button("Force reset").clicked() {
+            info!("Round reset from the admin panel");
+            force_reset(app);
+        }
+        if ui.button("Statistics").clicked() {
+            stats::open_stats_screen(app);
+        }
+    });
+}
+
+/// Editor for the settings which only affect the GUI and the bot, and can therefore be changed
+/// while the booth is running. Connection settings require a restart.
+fn render_settings_editor(app: &mut ApplicationState, ui: &mut egui::Ui) {
+    let draft = app.admin.draft.get_or_insert_with(|| (*app.settings).clone());
+
+    egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+        egui::Grid::new("admin_settings").num_columns(2).striped(true).show(ui, |ui| {
+            ui.label("Model");
+            egui::ComboBox::from_id_salt("admin_llm_model").selected_text(draft.llm_model.to_string()).show_ui(ui, |ui| {
+                ui.selectable_value(&mut draft.llm_model, LLMModel::GPT4o, LLMModel::GPT4o.to_string());
+                ui.selectable_value(&mut draft.llm_model, LLMModel::GPTo1, LLMModel::GPTo1.to_string());
+            });
+            ui.end_row();
+
+            ui.label("Persona");
+            let personas: Vec<String> = draft.personas.iter().map(|persona| persona.name.clone()).collect();
+            egui::ComboBox::from_id_salt("admin_persona").selected_text(draft.persona.clone().unwrap_or_else(|| "random".to_string())).show_ui(ui, |ui| {
+                ui.selectable_value(&mut draft.persona, None, "random");
+                for name in personas {
+                    ui.selectable_value(&mut draft.persona, Some(name.clone()), name);
+                }
+            });
+            ui.end_row();
+
+            ui.label("Default difficulty");
+            let difficulties: Vec<String> = draft.difficulties.iter().map(|difficulty| difficulty.name.clone()).collect();
+            egui::ComboBox::from_id_salt("admin_difficulty").selected_text(draft.default_difficulty.clone()).show_ui(ui, |ui| {
+                for name in difficulties {
+                    ui.selectable_value(&mut draft.default_difficulty, name.clone(), name);
+                }
+            });
+            ui.end_row();
+
+            ui.label("Debounce (ms)");
+            ui.add(DragValue::new(&mut draft.llm_debounce_ms).range(0..=10000));
+            ui.end_row();
+
+            ui.label("Take initiative after (s)");
+            ui.horizontal(|ui| {
+                ui.add(DragValue::new(&mut draft.llm_take_initiative_after_lower_bound).range(0..=600));
+                ui.add(DragValue::new(&mut draft.llm_take_initiative_after_upper_bound).range(0..=600));
+            });
+            ui.end_row();
+
+            ui.label("Take initiative probability");
+            ui.add(Slider::new(&mut draft.llm_take_initiative_probability, 0.0..=1.0));
+            ui.end_row();
+
+            ui.label("Follow up after (s)");
+            ui.horizontal(|ui| {
+                ui.add(DragValue::new(&mut draft.llm_no_response_after_lower_bound).range(0..=600));
+                ui.add(DragValue::new(&mut draft.llm_no_response_after_upper_bound).range(0..=600));
+            });
+            ui.end_row();
+
+            ui.label("Follow up probability");
+            ui.add(Slider::new(&mut draft.llm_no_response_probability, 0.0..=1.0));
+            ui.end_row();
+
+            ui.label("Typo rate");
+            ui.add(Slider::new(&mut draft.humanizer_typo_rate, 0.0..=1.0));
+            ui.end_row();
+
+            ui.label("Lowercase rate");
+            ui.add(Slider::new(&mut draft.humanizer_lowercase_rate, 0.0..=1.0));
+            ui.end_row();
+
+            ui.label("Split rate");
+            ui.add(Slider::new(&mut draft.humanizer_split_rate, 0.0..=1.0));
+            ui.end_row();
+
+            ui.label("Correction rate");
+            ui.add(Slider::new(&mut draft.humanizer_correction_rate, 0.0..=1.0));
+            ui.end_row();
+
+            ui.label("Max reply length (0 = off)");
+            ui.add(DragValue::new(&mut draft.output_filter_max_chars).range(0..=2000));
+            ui.end_row();
+
+            ui.label("Max regenerations");
+            ui.add(DragValue::new(&mut draft.output_filter_max_regenerations).range(0..=10));
+            ui.end_row();
+
+            ui.label("Moderation policy");
+            egui::ComboBox::from_id_salt("admin_moderation_policy").selected_text(format!("{:?}", draft.moderation_policy)).show_ui(ui, |ui| {
+                ui.selectable_value(&mut draft.moderation_policy, ModerationPolicy::Reject, "Reject");
+                ui.selectable_value(&mut draft.moderation_policy, ModerationPolicy::Redact, "Redact");
+                ui.selectable_value(&mut draft.moderation_policy, ModerationPolicy::Drop, "Drop");
+            });
+            ui.end_row();
+
+            ui.label("Record transcripts");
+            ui.checkbox(&mut draft.record_transcripts, "");
+            ui.end_row();
+        });
+    });
+
+    ui.horizontal(|ui| {
+        if ui.button("Apply").clicked() {
+            apply_settings(app);
+        }
+        if ui.button("Discard").clicked() {
+            app.admin.draft = None;
+        }
+    });
+}
+
+/// Replace the settings of the GUI and the bot with the edited ones
+fn apply_settings(app: &mut ApplicationState) {
+    let Some(mut draft) = app.admin.draft.take() else {
+        return;
+    };
+    if draft.llm_take_initiative_after_upper_bound < draft.llm_take_initiative_after_lower_bound {
+        draft.llm_take_initiative_after_upper_bound = draft.llm_take_initiative_after_lower_bound;
+    }
+    if draft.llm_no_response_after_upper_bound < draft.llm_no_response_after_lower_bound {
+        draft.llm_no_response_after_upper_bound = draft.llm_no_response_after_lower_bound;
+    }
+
+    let settings = Arc::new(draft);
+    info!("Settings changed in the admin panel");
+    app.humanizer.update_rates(&settings);
+    if let Some(typo_rate) = app.persona.as_ref().and_then(|persona| persona.typo_rate) {
+        app.humanizer.set_typo_rate(typo_rate);
+    }
+    app.settings = settings.clone();
+    if app.bot_sender.send(InterTaskMessageToBot::UpdateSettings { settings }).is_err() {
+        error!("Channel to bot task is closed, the bot keeps the old settings");
+    }
+}
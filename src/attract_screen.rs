@@ -0,0 +1,110 @@
+use std::time::SystemTime;
+use eframe::egui::{Context, Label, RichText};
+use eframe::{egui, Frame};
+use rand::seq::SliceRandom;
+use crate::rounds::{leaderboard, load_rounds, LeaderboardEntry, RoundStats};
+use crate::spectator::scrub_personal_data;
+use crate::{transcripts, ApplicationState, Screen};
+
+/// Number of quotes picked from the transcripts each time the attract mode starts
+const QUOTES: usize = 5;
+/// Places shown on the leaderboard
+const LEADERBOARD_PLACES: usize = 5;
+
+/// What the attract mode shows, loaded when it starts
+#[derive(Debug, Default)]
+pub struct AttractContent {
+    since: Option<SystemTime>,
+    quotes: Vec<String>,
+    stats: RoundStats,
+    leaderboard: Vec<LeaderboardEntry>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Slide {
+    Rules,
+    Quote,
+    DetectionRate,
+    Leaderboard,
+}
+
+/// Switch to the attract mode and load quotes and stats for it
+pub fn enter_attract_mode(app: &mut ApplicationState) {
+    let mut quotes: Vec<String> = transcripts::load_transcripts(&app.settings.transcripts_dir).into_iter()
+        .flatten()
+        .filter(|msg| (15..=120).contains(&msg.chars().count()))
+        // Shown to everyone passing by, so scrubbed like the spectator feed
+        .map(|msg| scrub_personal_data(&msg))
+        .collect();
+    quotes.shuffle(&mut rand::thread_rng());
+    quotes.truncate(QUOTES);
+
+    let records = load_rounds(&app.settings.rounds_file);
+
+    app.attract = AttractContent {
+        since: Some(SystemTime::now()),
+        quotes,
+        stats: RoundStats::from_records(records.iter()),
+        leaderboard: leaderboard(&records, LEADERBOARD_PLACES),
+    };
+    app.screen = Screen::Attract;
+}
+
+pub fn render_attract_screen(app: &mut ApplicationState, ctx: &Context, _frame: &mut Frame) {
+    let mut slides = vec![Slide::Rules];
+    if !app.attract.quotes.is_empty() {
+        slides.push(Slide::Quote);
+    }
+    if app.attract.stats.detection_rate().is_some() {
+        slides.push(Slide::DetectionRate);
+    }
+    if !app.attract.leaderboard.is_empty() {
+        slides.push(Slide::Leaderboard);
+    }
+
+    let elapsed = app.attract.since.and_then(|since| since.elapsed().ok()).map_or(0, |elapsed| elapsed.as_secs());
+    let slide_number = (elapsed / app.settings.attract_slide_secs.max(1)) as usize;
+    let slide = slides[slide_number % slides.len()];
+
+    egui::CentralPanel::default().show(ctx, |ui| {
+        ctx.request_repaint_after(std::time::Duration::from_secs(1));
+        ui.vertical_centered(|ui| {
+            ui.add_space(60.0);
+            ui.heading(app.text("title"));
+            ui.add_space(60.0);
+
+            match slide {
+                Slide::Rules => {
+                    ui.add(Label::new(RichText::new(app.start_rules()).size(30.0)).wrap());
+                },
+                Slide::Quote => {
+                    // Another quote every time the slide comes up
+                    let quote = &app.attract.quotes[(slide_number / slides.len()) % app.attract.quotes.len()];
+                    ui.label(RichText::new(app.text("attract_quote_heading")).size(24.0));
+                    ui.add_space(20.0);
+                    ui.add(Label::new(RichText::new(format!("\"{}\"", quote)).size(40.0).italics()).wrap());
+                    ui.add_space(20.0);
+                    ui.label(RichText::new(app.text("attract_quote_question")).size(24.0));
+                },
+                Slide::DetectionRate => {
+                    let rate = app.attract.stats.detection_rate().unwrap_or(0.0);
+                    ui.label(RichText::new(format!("{:.0} %", rate * 100.0)).size(80.0).strong());
+                    ui.add_space(20.0);
+                    ui.label(RichText::new(app.localization.format(&app.language, "attract_detection_rate", &[
+                        ("rounds", app.attract.stats.guessed.to_string()),
+                    ])).size(30.0));
+                },
+                Slide::Leaderboard => {
+                    ui.label(RichText::new(app.text("attract_leaderboard")).size(40.0));
+                    ui.add_space(20.0);
+                    for (place, entry) in app.attract.leaderboard.iter().enumerate() {
+                        ui.label(RichText::new(format!("{}. {} - {}/{}", place + 1, entry.name, entry.stats.correct, entry.stats.guessed)).size(30.0));
+                    }
+                },
+            }
+
+            ui.add_space(80.0);
+            ui.label(RichText::new(app.text("attract_touch_to_play")).size(30.0).strong());
+        });
+    });
+}
@@ -0,0 +1,493 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use reqwest::Client;
+use tokio::sync::broadcast;
+use tracing::{debug, error, info, info_span, warn, Instrument, Span};
+use crate::difficulty::Difficulty;
+use crate::i18n::Localization;
+use crate::persona::Persona;
+use crate::llm_context::trim_history;
+use crate::output_filter::{filter_reply, FilterOutcome, FilterPrompts};
+use crate::{settings, InterTaskMessageToBot, InterTaskMessageToGUI, LLMMessage, LLMModel, LLMRequest, LLMResponse, LLMResponseBundle, PlayerMessage};
+use crate::logging::CHAT;
+
+/// Message waiting for an answer of the LLM
+struct PendingLLMMessage {
+    msg: PlayerMessage,
+    steering: bool,
+}
+
+/// Developer prompts the bot starts a round with, in the order they are sent to the LLM: the
+/// persona's base prompt (or the default initial prompt), the strictness prompt of the difficulty
+/// and the already layered custom prompt of the player.
+pub fn system_prompts(localization: &Localization, language: &str, persona: Option<&Persona>, difficulty: Option<&Difficulty>, custom_prompt: Option<String>) -> Vec<String> {
+    let initial_prompt = match persona {
+        Some(persona) => localization.fill_in(&persona.base_prompt),
+        None => localization.initial_prompt(language),
+    };
+    let strictness_prompt = difficulty.map(|difficulty| localization.strictness_prompt(language, difficulty)).unwrap_or_default();
+    std::iter::once(initial_prompt)
+        .chain((!strictness_prompt.is_empty()).then_some(strictness_prompt))
+        .chain(custom_prompt)
+        .collect()
+}
+
+/// Start the bot task, which plays the AI the opponent chats with. It owns the conversation with
+/// the LLM, gets the opponent's messages from the GUI and hands the replies back to the GUI.
+///
+/// Messages arriving in quick succession are answered together once the debounce time passed.
+/// A new message cancels the request in flight (including the simulated typing delay), the LLM
+/// then answers all unanswered messages at once.
+///
+/// If the opponent stays quiet, the bot opens the conversation itself after a random time and
+/// follows up on its own messages which were left unanswered.
+pub fn spawn_bot_task(settings: Arc<settings::Settings>, localization: Arc<Localization>, sender_to_gui: broadcast::Sender<InterTaskMessageToGUI>) -> broadcast::Sender<InterTaskMessageToBot> {
+    let (bot_sender, mut receiver) = broadcast::channel::<InterTaskMessageToBot>(30);
+
+    tokio::spawn(async move {
+        let mut settings = settings;
+        let client = Client::new();
+        let mut history: Vec<LLMMessage> = vec![];
+        let mut language = localization.default_language().to_string();
+        let mut persona: Option<Persona> = None;
+        let mut model = settings.llm_model;
+        let mut lower_delay_limit = 2.0;
+        let mut upper_delay_limit = 3.5;
+        let mut pending: Vec<PendingLLMMessage> = vec![];
+        let mut debounce_until: Option<tokio::time::Instant> = None;
+        let mut in_flight: Option<Pin<Box<dyn Future<Output = Option<LLMResponseBundle>> + Send>>> = None;
+        let mut take_initiative_at: Option<tokio::time::Instant> = None;
+        let mut follow_up_at: Option<tokio::time::Instant> = None;
+        let mut round_span = Span::none();
+
+        debug!("Started bot task");
+
+        loop {
+            tokio::select! {
+                msg = receiver.recv() => {
+                    match msg {
+                        Ok(InterTaskMessageToBot::StartRound { language: round_language, persona: round_persona, difficulty, custom_prompt, round_id, lower_delay_limit: lower, upper_delay_limit: upper }) => {
+                            round_span = info_span!("round", id = %round_id);
+                            language = round_language;
+                            persona = round_persona.map(|persona| *persona);
+                            history = system_prompts(&localization, &language, persona.as_ref(), difficulty.as_ref(), custom_prompt)
+                                .into_iter()
+                                .map(|content| LLMMessage {
+                                    role: "developer".to_string(),
+                                    content,
+                                    refusal: None,
+                                })
+                                .collect();
+                            model = difficulty.as_ref().and_then(|difficulty| difficulty.model).unwrap_or(settings.llm_model);
+                            lower_delay_limit = lower;
+                            upper_delay_limit = upper;
+                            pending.clear();
+                            debounce_until = None;
+                            in_flight = None;
+                            take_initiative_at = random_deadline(
+                                settings.llm_take_initiative_after_lower_bound,
+                                settings.llm_take_initiative_after_upper_bound,
+                                settings.llm_take_initiative_probability,
+                            );
+                            follow_up_at = None;
+                        },
+                        Ok(InterTaskMessageToBot::OpponentMessage { msg }) => {
+                            // Real messages replace pending steering prompts
+                            pending.retain(|pending_msg| !pending_msg.steering);
+                            if in_flight.take().is_some() {
+                                debug!(parent: &round_span, "Cancelled LLM request in flight, answering all pending messages together");
+                            }
+                            pending.push(PendingLLMMessage { msg, steering: false });
+                            take_initiative_at = None;
+                            follow_up_at = None;
+                            debounce_until = Some(tokio::time::Instant::now() + Duration::from_millis(settings.llm_debounce_ms));
+                        },
+                        Ok(InterTaskMessageToBot::UpdateSettings { settings: new_settings }) => {
+                            settings = new_settings;
+                        },
+                        Ok(InterTaskMessageToBot::EndRound) => {
+                            history.clear();
+                            round_span = Span::none();
+                            persona = None;
+                            pending.clear();
+                            debounce_until = None;
+                            in_flight = None;
+                            take_initiative_at = None;
+                            follow_up_at = None;
+                        },
+                        Err(broadcast::error::RecvError::Lagged(n)) => {
+                            warn!("Bot task lagged behind by {} messages", n);
+                        },
+                        Err(broadcast::error::RecvError::Closed) => {
+                            info!("Channel to bot was closed, stopping bot task");
+                            break;
+                        }
+                    }
+                },
+                _ = tokio::time::sleep_until(debounce_until.unwrap_or_else(tokio::time::Instant::now)), if debounce_until.is_some() => {
+                    debounce_until = None;
+                    if let Some(msg) = merge_pending_messages(&pending) {
+                        let steering = pending.iter().all(|pending_msg| pending_msg.steering);
+                        let request = talk_to_llm(msg, history.clone(), client.clone(), settings.clone(), model, steering, FilterPrompts::new(&localization, &language));
+                        let status_sender = sender_to_gui.clone();
+                        in_flight = Some(Box::pin(async move {
+                            match tokio::time::timeout(Duration::from_secs(30), request).await {
+                                Ok(resp) => {
+                                    // Report the request before the simulated typing delay
+                                    let _ = status_sender.send(InterTaskMessageToGUI::LLMStatus { latency: resp.latency, error: resp.error.clone() });
+                                    if let Some(msg) = &resp.new_message_from_llm {
+                                        tokio::time::sleep(typing_delay(msg.chars().count(), lower_delay_limit, upper_delay_limit)).await;
+                                    }
+                                    Some(resp)
+                                },
+                                Err(_) => {
+                                    error!("Couldn't contact LLM, timeout exceeded");
+                                    let _ = status_sender.send(InterTaskMessageToGUI::LLMStatus { latency: None, error: Some("Timeout exceeded".to_string()) });
+                                    None
+                                }
+                            }
+                        }.instrument(round_span.clone())));
+                    }
+                },
+                _ = tokio::time::sleep_until(take_initiative_at.unwrap_or_else(tokio::time::Instant::now)), if take_initiative_at.is_some() => {
+                    take_initiative_at = None;
+                    // The opponent may already be waiting for an answer, no need to steer then
+                    if !pending.is_empty() || in_flight.is_some() {
+                        continue;
+                    }
+                    info!(parent: &round_span, "Opponent stays quiet, taking the initiative");
+                    let opener = persona.as_ref().and_then(|persona| persona.openers.choose(&mut rand::thread_rng()).cloned());
+                    if let Some(opener) = opener {
+                        // Openers of the persona are sent as they are, without asking the LLM
+                        let mut new_history = history.clone();
+                        new_history.push(LLMMessage {
+                            role: "assistant".to_string(),
+                            content: opener.clone(),
+                            refusal: None,
+                        });
+                        let delay = typing_delay(opener.chars().count(), lower_delay_limit, upper_delay_limit);
+                        in_flight = Some(Box::pin(async move {
+                            tokio::time::sleep(delay).await;
+                            Some(LLMResponseBundle {
+                                new_message_from_llm: Some(opener),
+                                history: new_history,
+                                regenerations: 0,
+                                filtered: false,
+                                latency: None,
+                                error: None,
+                            })
+                        }));
+                    } else {
+                        pending.push(PendingLLMMessage { msg: steering_message(&localization.take_initiative_prompt(&language)), steering: true });
+                        debounce_until = Some(tokio::time::Instant::now());
+                    }
+                },
+                _ = tokio::time::sleep_until(follow_up_at.unwrap_or_else(tokio::time::Instant::now)), if follow_up_at.is_some() => {
+                    follow_up_at = None;
+                    if pending.is_empty() {
+                        info!(parent: &round_span, "No response from opponent, following up");
+                        pending.push(PendingLLMMessage { msg: steering_message(&localization.no_response_prompt(&language)), steering: true });
+                        debounce_until = Some(tokio::time::Instant::now());
+                    }
+                },
+                resp = async { in_flight.as_mut().unwrap().await }, if in_flight.is_some() => {
+                    in_flight = None;
+                    pending.clear();
+                    if let Some(resp) = resp {
+                        history = resp.history;
+                        if let Some(msg) = resp.new_message_from_llm {
+                            follow_up_at = random_deadline(
+                                settings.llm_no_response_after_lower_bound,
+                                settings.llm_no_response_after_upper_bound,
+                                settings.llm_no_response_probability,
+                            );
+                            if sender_to_gui.send(InterTaskMessageToGUI::BotReply { msg, regenerations: resp.regenerations, filtered: resp.filtered }).is_err() {
+                                info!("Channel to GUI was closed, stopping bot task");
+                                break;
+                            }
+                        }
+                    }
+                },
+            }
+        }
+    });
+
+    bot_sender
+}
+
+/// Random point in time between `lower` and `upper` seconds from now, or `None` if the event
+/// shouldn't happen at all
+fn random_deadline(lower: u64, upper: u64, probability: f64) -> Option<tokio::time::Instant> {
+    let mut rng = rand::thread_rng();
+    if !rng.gen_bool(probability.clamp(0.0, 1.0)) {
+        return None;
+    }
+    let secs = rng.gen_range(lower..=upper.max(lower));
+    Some(tokio::time::Instant::now() + Duration::from_secs(secs))
+}
+
+/// Instruction to the LLM which is not part of the conversation
+pub fn steering_message(prompt: &str) -> PlayerMessage {
+    PlayerMessage {
+        msg: prompt.to_string(),
+        from_ai: false,
+        to_ai: false,
+        timestamp: SystemTime::now(),
+        regenerations: 0,
+        filtered: false,
+    }
+}
+
+/// Combine all pending messages into one message to the LLM
+fn merge_pending_messages(pending: &[PendingLLMMessage]) -> Option<PlayerMessage> {
+    let first = pending.first()?;
+    Some(PlayerMessage {
+        msg: pending.iter().map(|pending_msg| pending_msg.msg.msg.as_str()).collect::<Vec<&str>>().join("\n"),
+        ..first.msg.clone()
+    })
+}
+
+/// Slowest and fastest typing speed the bot simulates, in chars per second
+pub const MIN_CHARS_PER_SECOND: f32 = 0.5;
+pub const MAX_CHARS_PER_SECOND: f32 = 30.0;
+/// Typing speed used if the limits don't leave a range to pick from
+const FALLBACK_CHARS_PER_SECOND: f32 = 2.5;
+
+/// Random delay for a message of the given length, simulating human typing speed
+pub fn typing_delay(num_of_chars: usize, lower_delay_limit: f32, upper_delay_limit: f32) -> Duration {
+    // max/min also replace NaN, so the range below is always valid
+    let lower = lower_delay_limit.max(MIN_CHARS_PER_SECOND);
+    let upper = upper_delay_limit.min(MAX_CHARS_PER_SECOND);
+    let chars_per_second = if lower < upper {
+        rand::thread_rng().gen_range(lower..upper)
+    } else {
+        FALLBACK_CHARS_PER_SECOND
+    };
+
+    let delay = num_of_chars as f32 / chars_per_second;
+    let delay_in_ms = (delay * 100.0) as u64;
+    debug!("Delaying response by {} ms aka {} chars per second", delay_in_ms, chars_per_second);
+    Duration::from_millis(delay_in_ms)
+}
+
+/// Ask the LLM to answer `msg`. Steering prompts (e.g. "take the initiative") are sent as developer
+/// message and are not stored in the returned history, only the reply is.
+pub(crate) async fn talk_to_llm(msg: PlayerMessage, mut history: Vec<LLMMessage>, client: reqwest::Client, settings: Arc<settings::Settings>, model: LLMModel, steering: bool, prompts: FilterPrompts) -> LLMResponseBundle {
+    let prompt = LLMMessage {
+        role: if steering { "developer" } else { "user" }.to_string(),
+        content: msg.msg,
+        refusal: None,
+    };
+    if !steering {
+        history.push(prompt.clone());
+    }
+    trim_history(&mut history, settings.llm_history_token_budget);
+
+    let request_messages = |history: &Vec<LLMMessage>| {
+        let mut messages = history.clone();
+        if steering {
+            messages.push(prompt.clone());
+        }
+        messages
+    };
+
+    let mut new_msg = None;
+    let mut regenerations = 0;
+    let mut filtered = false;
+    let mut latency = None;
+    let mut error = None;
+    let mut messages = request_messages(&history);
+
+    loop {
+        let request_start = Instant::now();
+        let reply = match request_completion(&client, &settings, model, messages.clone()).await {
+            Ok(reply) => {
+                latency = Some(request_start.elapsed());
+                reply
+            },
+            Err(e) => {
+                error = Some(e);
+                break;
+            }
+        };
+
+        let outcome = match &reply.refusal {
+            Some(refusal) => FilterOutcome::Rejected(format!("refusal \"{}\"", refusal)),
+            None => filter_reply(&reply.content, &settings),
+        };
+
+        match outcome {
+            FilterOutcome::Accepted(text) => {
+                filtered |= text != reply.content;
+                history.push(LLMMessage {
+                    content: text.clone(),
+                    ..reply
+                });
+                new_msg = Some(text);
+                break;
+            },
+            FilterOutcome::Rejected(reason) => {
+                info!("Rejected LLM reply ({})", reason);
+                debug!(target: CHAT, "Rejected reply: {}", reply.content);
+                filtered = true;
+
+                if regenerations >= settings.output_filter_max_regenerations {
+                    // Don't let the bot give itself away, rather use an inconspicuous fallback
+                    let fallback = prompts.fallback_replies.choose(&mut rand::thread_rng()).cloned();
+                    if let Some(fallback) = &fallback {
+                        history.push(LLMMessage {
+                            role: "assistant".to_string(),
+                            content: fallback.clone(),
+                            refusal: None,
+                        });
+                    }
+                    new_msg = fallback;
+                    break;
+                }
+
+                regenerations += 1;
+                messages = request_messages(&history);
+                messages.push(LLMMessage {
+                    role: "developer".to_string(),
+                    content: prompts.regenerate_prompt.clone(),
+                    refusal: None,
+                });
+            }
+        }
+    }
+
+    LLMResponseBundle {
+        new_message_from_llm: new_msg,
+        history,
+        regenerations,
+        filtered,
+        latency,
+        error,
+    }
+}
+
+/// Send the conversation to the LLM and return its reply
+async fn request_completion(client: &reqwest::Client, settings: &settings::Settings, model: LLMModel, messages: Vec<LLMMessage>) -> Result<LLMMessage, String> {
+    let request = LLMRequest {
+        model,
+        messages,
+    };
+
+    debug!(target: CHAT, "Sending request to LLM: {:?}", request);
+    let res = client.post("https://api.openai.com/v1/chat/completions")
+        .header("Authorization", format!("Bearer {}", settings.openai_api_key.expose()))
+        .json(&request)
+        .send().await;
+
+    match res {
+        Ok(res) => {
+            let res = res.json::<LLMResponse>().await;
+            match res {
+                Ok(res) => {
+                    debug!(target: CHAT, "Received response from LLM: {:?}", res);
+                    if let Some(res) = res.choices.first() {
+                        if res.finish_reason != "stop" {
+                            error!("LLM didn't finish conversation. This is unexpected!");
+                        }
+                        Ok(res.message.clone())
+                    } else {
+                        error!("LLM didn't return any choices. This is unexpected!");
+                        Err("LLM didn't return any choices".to_string())
+                    }
+                }
+                Err(e) => {
+                    error!("Couldn't decode response from LLM: {}", e);
+                    Err(format!("Couldn't decode response: {}", e))
+                }
+            }
+        }
+        Err(e) => {
+            error!("Couldn't send request: {}", e);
+            Err(format!("Couldn't send request: {}", e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(msg: &str, steering: bool) -> PendingLLMMessage {
+        PendingLLMMessage { msg: PlayerMessage { to_ai: true, ..steering_message(msg) }, steering }
+    }
+
+    #[test]
+    fn pending_messages_are_answered_together() {
+        assert!(merge_pending_messages(&[]).is_none());
+        let merged = merge_pending_messages(&[pending("hi", false), pending("you there?", false)]).unwrap();
+        assert_eq!(merged.msg, "hi\nyou there?");
+        assert!(merged.to_ai);
+    }
+
+    #[test]
+    fn deadlines_respect_probability_and_bounds() {
+        assert!(random_deadline(1, 5, 0.0).is_none());
+        assert!(random_deadline(1, 5, -1.0).is_none());
+        for _ in 0..50 {
+            let deadline = random_deadline(10, 20, 1.0).unwrap();
+            let secs = deadline.duration_since(tokio::time::Instant::now()).as_secs_f64();
+            assert!((9.0..=20.0).contains(&secs), "{}", secs);
+        }
+        // Swapped bounds don't panic
+        assert!(random_deadline(20, 10, 1.0).is_some());
+    }
+
+    #[test]
+    fn system_prompts_are_layered_in_order() {
+        let settings = settings::Settings {
+            initial_prompt: "Imitate a human at {event_name}.".to_string(),
+            event_name: "the fair".to_string(),
+            ..settings::Settings::default()
+        };
+        let localization = Localization::load(&settings);
+        let persona = Persona {
+            name: "pirate".to_string(),
+            language: None,
+            base_prompt: "Be a pirate at {event_name}.".to_string(),
+            typing_speed_multiplier: 1.0,
+            typo_rate: None,
+            openers: vec![],
+        };
+        let difficulty = Difficulty {
+            name: "hard".to_string(),
+            model: None,
+            strictness_prompt: "Never break character.".to_string(),
+            humanizer_intensity: 1.0,
+            typing_speed_multiplier: 1.0,
+            game_duration_secs: 210,
+        };
+
+        assert_eq!(system_prompts(&localization, "en", None, None, None), vec!["Imitate a human at the fair."]);
+        assert_eq!(
+            system_prompts(&localization, "en", Some(&persona), Some(&difficulty), Some("Talk about ships.".to_string())),
+            vec!["Be a pirate at the fair.", "Never break character.", "Talk about ships."],
+        );
+        let lenient = Difficulty { strictness_prompt: String::new(), ..difficulty };
+        assert_eq!(system_prompts(&localization, "en", None, Some(&lenient), None).len(), 1);
+    }
+
+    #[test]
+    fn typing_delay_survives_unusable_limits() {
+        for (lower, upper) in [(3.0, 3.0), (4.0, 2.0), (0.0, 0.0), (f32::NAN, 3.0), (2.0, f32::INFINITY), (f32::NAN, f32::NAN), (-1.0, 0.1)] {
+            let delay = typing_delay(10, lower, upper);
+            assert!(delay <= Duration::from_millis((10.0 / MIN_CHARS_PER_SECOND * 100.0) as u64), "{} {}", lower, upper);
+        }
+    }
+
+    #[test]
+    fn typing_delay_stays_within_limits() {
+        for _ in 0..100 {
+            let delay = typing_delay(100, 2.0, 4.0);
+            assert!(delay >= Duration::from_millis(2500) && delay <= Duration::from_millis(5000));
+        }
+    }
+}
@@ -2,31 +2,39 @@ use std::fs::File;
 use std::io::BufReader;
 use tokio_rustls::rustls::pki_types::{CertificateDer, CertificateRevocationListDer, PrivateKeyDer};
 use tokio_rustls::rustls::RootCertStore;
+use crate::network::build_tls_configs;
+use crate::settings::Settings;
 
-pub fn load_root_ca(path: String) -> RootCertStore {
+pub fn load_root_ca(path: String) -> Result<RootCertStore, String> {
     // Load certificates
     let mut root_store = tokio_rustls::rustls::RootCertStore::empty();
-    let ca_file = File::open(&path).expect("cannot open CA file");
+    let ca_file = File::open(&path).map_err(|e| format!("Cannot open CA file {}: {}", path, e))?;
     let mut reader = BufReader::new(ca_file);
-    let certs: Vec<_> = rustls_pemfile::certs(&mut reader).map(|cert| cert.expect("Couldn't parse root CA")).collect();
-    for cert in certs{
-        root_store.add(cert).expect("Couldn't add CA file to root store.");
+    for cert in rustls_pemfile::certs(&mut reader){
+        let cert = cert.map_err(|e| format!("Couldn't parse root CA {}: {}", path, e))?;
+        root_store.add(cert).map_err(|e| format!("Couldn't add CA file {} to root store: {}", path, e))?;
     }
-    root_store
+    if root_store.is_empty() {
+        return Err(format!("No certificate in CA file {}", path));
+    }
+    Ok(root_store)
 }
 
-pub fn load_client_cert(path: String) -> Vec<CertificateDer<'static>>{
-    let file = File::open(&path).expect("cannot open client cert file");
+pub fn load_client_cert(path: String) -> Result<Vec<CertificateDer<'static>>, String>{
+    let file = File::open(&path).map_err(|e| format!("Cannot open client cert file {}: {}", path, e))?;
     let mut reader = BufReader::new(file);
-    let certs = rustls_pemfile::certs(&mut reader);
-    let certs = certs.map(|cert|cert.expect("Couldn't parse cert file")).collect();
-    certs
+    let certs: Vec<_> = rustls_pemfile::certs(&mut reader).collect::<Result<_, _>>().map_err(|e| format!("Couldn't parse cert file {}: {}", path, e))?;
+    if certs.is_empty() {
+        return Err(format!("No certificate in cert file {}", path));
+    }
+    Ok(certs)
 }
 
-pub fn load_private_key(path: String) -> PrivateKeyDer<'static>{
-    let file = File::open(&path).expect("cannot open client key file");
+pub fn load_private_key(path: String) -> Result<PrivateKeyDer<'static>, String>{
+    let file = File::open(&path).map_err(|e| format!("Cannot open client key file {}: {}", path, e))?;
     let mut reader = BufReader::new(file);
-    rustls_pemfile::private_key(&mut reader).expect("Couldn't parse Private key file!").expect("Missing private key")
+    rustls_pemfile::private_key(&mut reader).map_err(|e| format!("Couldn't parse private key file {}: {}", path, e))?
+        .ok_or_else(|| format!("Missing private key in {}", path))
 }
 
 pub fn load_crl(path: String) -> Vec<CertificateRevocationListDer<'static>>{
@@ -35,4 +43,10 @@ pub fn load_crl(path: String) -> Vec<CertificateRevocationListDer<'static>>{
     let res = rustls_pemfile::crls(&mut crl_reader).map(|cert|cert.expect("Couldn't load CRL!")).collect();
 
     res
-}
\ No newline at end of file
+}
+
+/// Build the mtls configs exactly like the network task does, to check the configured certificates
+pub fn check_certs(settings: &Settings) -> Result<String, String> {
+    build_tls_configs(settings)?;
+    Ok(format!("Root CA {}, client cert {} and client key {} are valid", settings.root_ca, settings.client_cert, settings.client_key))
+}
@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+use clap::{Parser, Subcommand};
+use crate::settings::Settings;
+
+/// Command line of the booth. The options override the matching settings of the config.
+#[derive(Parser, Debug)]
+#[command(version, about = "The Turing Challenge booth")]
+pub struct Cli {
+    /// Config file read on top of config/default.toml, it only needs the settings that differ
+    #[arg(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Name of this booth in the metrics and logs, overrides `booth_name`
+    #[arg(long, global = true, value_name = "NAME")]
+    pub booth_name: Option<String>,
+    /// Address of the other booth, overrides `connect_to_host`
+    #[arg(long, global = true, value_name = "HOST:PORT")]
+    pub connect: Option<String>,
+    /// Address to listen on for the other booth, overrides `bind_to_host` and `port`
+    #[arg(long, global = true, value_name = "HOST:PORT")]
+    pub listen: Option<String>,
+    /// Show the main window in fullscreen
+    #[arg(long, global = true)]
+    pub fullscreen: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Run the booth, the default
+    Run,
+    /// Check the config and print the resulting settings, secrets redacted
+    CheckConfig,
+    /// Check the mtls certificates from the config
+    Certs,
+    /// Print the statistics of all rounds or export them as CSV
+    Stats {
+        /// Export the rounds as CSV to this file instead, without player names
+        #[arg(long, value_name = "FILE")]
+        csv: Option<String>,
+    },
+}
+
+impl Cli {
+    /// Read the settings and apply the options given on the command line
+    pub fn settings(&self) -> Result<Settings, String> {
+        let mut settings = Settings::new(self.config.as_deref()).map_err(|e| e.to_string())?;
+
+        if let Some(booth_name) = &self.booth_name {
+            settings.booth_name = booth_name.clone();
+        }
+        if let Some(connect) = &self.connect {
+            settings.connect_to_host = connect.clone();
+        }
+        if let Some(listen) = &self.listen {
+            let (host, port) = listen.rsplit_once(':').ok_or_else(|| format!("--listen needs HOST:PORT, got {}", listen))?;
+            settings.port = port.parse().map_err(|_| format!("Invalid port in --listen: {}", port))?;
+            settings.bind_to_host = host.to_string();
+        }
+        if self.fullscreen {
+            settings.fullscreen = true;
+        }
+
+        Ok(settings)
+    }
+}
@@ -0,0 +1,83 @@
+use serde::Deserialize;
+use crate::LLMModel;
+
+/// Preset tuning the bot and the round length, configured as `[[difficulties]]` in the settings.
+/// Both booths have to agree on one before the prompting starts.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Difficulty {
+    /// Identifier sent to the opponent, the label is the i18n string `difficulty_<name>`
+    pub name: String,
+    /// Model the bot uses, `llm_model` if unset
+    #[serde(default)]
+    pub model: Option<LLMModel>,
+    /// Added to the initial prompt, e.g. to make the bot stick more closely to its persona. The
+    /// catalogs can translate it under `[prompts.strictness_prompts]`.
+    #[serde(default)]
+    pub strictness_prompt: String,
+    /// Factor for all humanizer rates (typos, lowercase, splitting, corrections)
+    #[serde(default = "default_multiplier")]
+    pub humanizer_intensity: f64,
+    /// Factor for the simulated typing speed, > 1 types faster
+    #[serde(default = "default_multiplier_f32")]
+    pub typing_speed_multiplier: f32,
+    /// Length of the chat phase in seconds
+    #[serde(default = "default_game_duration_secs")]
+    pub game_duration_secs: u64,
+}
+
+fn default_multiplier() -> f64 {
+    1.0
+}
+
+fn default_multiplier_f32() -> f32 {
+    1.0
+}
+
+pub fn default_game_duration_secs() -> u64 {
+    210
+}
+
+/// The difficulty both booths play with. If the players picked different ones at the same time,
+/// the one listed first in the settings wins, so both sides come to the same result.
+pub fn agree_on_difficulty<'a>(difficulties: &'a [Difficulty], own: &str, opponent: Option<&str>) -> Option<&'a Difficulty> {
+    difficulties.iter()
+        .find(|difficulty| difficulty.name == own || Some(difficulty.name.as_str()) == opponent)
+        .or_else(|| difficulties.first())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn difficulties() -> Vec<Difficulty> {
+        ["easy", "normal", "hard"].iter().map(|name| Difficulty {
+            name: name.to_string(),
+            model: None,
+            strictness_prompt: String::new(),
+            humanizer_intensity: 1.0,
+            typing_speed_multiplier: 1.0,
+            game_duration_secs: default_game_duration_secs(),
+        }).collect()
+    }
+
+    #[test]
+    fn same_pick_is_played() {
+        let difficulties = difficulties();
+        assert_eq!(agree_on_difficulty(&difficulties, "hard", Some("hard")).unwrap().name, "hard");
+        assert_eq!(agree_on_difficulty(&difficulties, "hard", None).unwrap().name, "hard");
+    }
+
+    #[test]
+    fn tie_is_resolved_the_same_on_both_sides() {
+        let difficulties = difficulties();
+        assert_eq!(agree_on_difficulty(&difficulties, "hard", Some("normal")).unwrap().name, "normal");
+        assert_eq!(agree_on_difficulty(&difficulties, "normal", Some("hard")).unwrap().name, "normal");
+    }
+
+    #[test]
+    fn unknown_picks_fall_back_to_the_first() {
+        let difficulties = difficulties();
+        assert_eq!(agree_on_difficulty(&difficulties, "insane", Some("")).unwrap().name, "easy");
+        assert!(agree_on_difficulty(&[], "hard", Some("hard")).is_none());
+    }
+}
@@ -1,11 +1,13 @@
-use crate::egui::RichText;
 use std::time::SystemTime;
-use eframe::egui::{Color32, Context, Margin, ScrollArea, Separator};
+use eframe::egui::{Color32, Context, Margin, ScrollArea, Separator, Slider, TextEdit};
 use eframe::{egui, Frame};
 use egui_extras::{Size, StripBuilder};
+use tracing::{info};
 use crate::ApplicationState;
 
 pub fn render_end_screen(app: &mut ApplicationState, ctx: &Context, frame: &mut Frame){
+    render_guess_panel(app, ctx);
+
     egui::CentralPanel::default().show(ctx, |ui| {
         let total_width = ui.available_width();
         let content_width = total_width*0.9;
@@ -22,7 +24,7 @@ pub fn render_end_screen(app: &mut ApplicationState, ctx: &Context, frame: &mut
                 strip.cell(|ui|{
                     ui.vertical_centered(|ui|{
                         ui.add_space(10.0);
-                        ui.heading("Which Chat belongs to the AI?");
+                        ui.heading(app.text("end_heading"));
                         ui.add_space(10.0);
                         StripBuilder::new(ui)
                             .size(Size::exact((content_width/2.0)-15.0))
@@ -33,7 +35,7 @@ pub fn render_end_screen(app: &mut ApplicationState, ctx: &Context, frame: &mut
                                     let mut frame = egui::Frame::default();
                                     frame = frame.inner_margin(Margin::same(5.0)).fill(Color32::from_hex("#261A66").unwrap());
                                     frame.show(ui, |ui|{
-                                        ui.label("Chat #1:");
+                                        ui.label(app.text("chat_1"));
                                         let history_space = ui.available_height() - 80.0;
                                         ScrollArea::vertical().stick_to_bottom(true).max_height(history_space).show(ui, |ui|{
                                             let max_width = ui.available_width();
@@ -43,18 +45,8 @@ pub fn render_end_screen(app: &mut ApplicationState, ctx: &Context, frame: &mut
                                             crate::game_screen::show_messages(ui, &app.chat1_history, max_width);
                                         });
                                         ui.separator();
-                                        if ui.button("I think this is the AI chat").clicked(){
-                                            if app.human_chat == 1{
-                                                // Correct
-                                                app.correctly_guessed = Some(true);
-                                                println!("Player guessed correctly");
-                                            }else{
-                                                // Incorrect
-                                                app.correctly_guessed = Some(false);
-                                                println!("Player guessed incorrectly");
-                                            }
-                                            app.screen = crate::Screen::End2;
-                                            app.showing_end_screen_since = Some(SystemTime::now())
+                                        if ui.selectable_label(app.guessed_chat == Some(0), app.text("guess_ai_chat")).clicked(){
+                                            app.guessed_chat = Some(0);
                                         }
 
                                     });
@@ -66,7 +58,7 @@ pub fn render_end_screen(app: &mut ApplicationState, ctx: &Context, frame: &mut
                                     let mut frame = egui::Frame::default();
                                     frame = frame.inner_margin(Margin::same(5.0)).fill(Color32::from_hex("#261A66").unwrap());
                                     frame.show(ui, |ui|{
-                                        ui.label("Chat #2:");
+                                        ui.label(app.text("chat_2"));
                                         let history_space = ui.available_height() - 80.0;
 
                                         ScrollArea::vertical().stick_to_bottom(true).max_height(history_space).show(ui, |ui|{
@@ -77,18 +69,8 @@ pub fn render_end_screen(app: &mut ApplicationState, ctx: &Context, frame: &mut
                                             crate::game_screen::show_messages(ui, &app.chat2_history, max_width);
                                         });
                                         ui.separator();
-                                        if ui.button("I think this is the AI chat").clicked(){
-                                            if app.human_chat == 0{
-                                                // Correct
-                                                app.correctly_guessed = Some(true);
-                                                println!("Player guessed correctly");
-                                            }else{
-                                                // Incorrect
-                                                app.correctly_guessed = Some(false);
-                                                println!("Player guessed incorrectly");
-                                            }
-                                            app.screen = crate::Screen::End2;
-                                            app.showing_end_screen_since = Some(SystemTime::now())
+                                        if ui.selectable_label(app.guessed_chat == Some(1), app.text("guess_ai_chat")).clicked(){
+                                            app.guessed_chat = Some(1);
                                         }
                                     });
                                 });
@@ -102,37 +84,44 @@ pub fn render_end_screen(app: &mut ApplicationState, ctx: &Context, frame: &mut
     });
 }
 
-pub fn render_end_screen2(app: &mut ApplicationState, ctx: &Context, frame: &mut Frame){
-    egui::CentralPanel::default().show(ctx, |ui| {
-        let total_width = ui.available_width();
-        let content_width = total_width*0.9;
-        let side_width = (total_width - content_width)/2.0;
-
-        StripBuilder::new(ui)
-            .size(Size::exact(side_width))
-            .size(Size::exact(content_width))
-            .size(Size::exact(side_width))
-            .horizontal(|mut strip|{
-                strip.cell(|ui|{
-
-                });
-                strip.cell(|ui|{
-                    ui.vertical_centered(|ui|{
-                        ui.add_space(10.0);
-                        ui.heading("The Turing Challenge");
-                        ui.add_space(30.0);
-                        let text = if app.correctly_guessed.unwrap(){
-                            "Congratulations! You have successfully identified the AI chat."
-                        }else{
-                            "Sorry, you have failed to identify the AI chat :("
-                        };
-
-                        ui.label(RichText::from(text).size(40.0));
-                    });
-                });
-                strip.cell(|ui|{
-
-                });
+/// Confidence, reasoning and submit button below the chats
+fn render_guess_panel(app: &mut ApplicationState, ctx: &Context){
+    egui::TopBottomPanel::bottom("guess_panel").show(ctx, |ui|{
+        ui.vertical_centered(|ui|{
+            ui.add_space(10.0);
+            ui.horizontal(|ui|{
+                ui.label(app.text("guess_confidence"));
+                ui.add(Slider::new(&mut app.guess_confidence, 0..=100).suffix(" %"));
             });
+            ui.horizontal(|ui|{
+                ui.label(app.text("guess_reasoning"));
+                let hint = app.text("guess_reasoning_hint");
+                ui.add_sized([ui.available_width(), 25.0], TextEdit::singleline(&mut app.guess_reasoning).hint_text(hint));
+            });
+            ui.add_space(5.0);
+            if ui.add_enabled(app.guessed_chat.is_some(), egui::Button::new(app.text("submit_guess"))).clicked(){
+                submit_guess(app);
+            }
+            ui.add_space(10.0);
+        });
     });
-}
\ No newline at end of file
+}
+
+fn submit_guess(app: &mut ApplicationState){
+    let Some(guessed_chat) = app.guessed_chat else {
+        return;
+    };
+
+    // The chat which doesn't belong to the human is the AI chat
+    let correct = guessed_chat != app.human_chat;
+    if correct{
+        info!("Player guessed correctly with {} % confidence", app.guess_confidence);
+    }else{
+        info!("Player guessed incorrectly with {} % confidence", app.guess_confidence);
+    }
+    app.correctly_guessed = Some(correct);
+    crate::spectator::publish_result(app, correct);
+    app.metrics.guessed(correct);
+    app.screen = crate::Screen::Reveal;
+    app.showing_end_screen_since = Some(SystemTime::now())
+}
@@ -3,57 +3,18 @@ use eframe::egui::{Align, Color32, Context, Key, KeyboardShortcut, Margin, Modif
 use eframe::{egui, Frame};
 use egui_extras::{Size, StripBuilder};
 use rand::Rng;
-use crate::{ApplicationState, ChatMessage, ChatMessageOrigin, InterTaskMessageToNetworkTask, PlayerMessage, TcpMessage};
+use tracing::{debug};
+use crate::{spectator, ApplicationState, ChatMessage, ChatMessageOrigin, InterTaskMessageToNetworkTask, PlayerMessage, TcpMessage};
 
 pub fn render_game_screen(app: &mut ApplicationState, ctx: &Context, frame: &mut Frame) {
     let time_elapsed = app.game_start_time.unwrap().elapsed().unwrap().as_secs();
-    let time = if time_elapsed >= 210 {
+    let game_duration = app.game_duration_secs();
+    let time = if time_elapsed >= game_duration {
         0
     } else {
-        210 - time_elapsed
+        game_duration - time_elapsed
     };
 
-    // let the llm send the first message if the other player has not sent a message after a certain time
-    if !app.llm_chat_first_message && time_elapsed >= app.llm_take_iniative_after as u64 {
-        app.llm_chat_first_message = true;
-        app.llm_last_message_time = Some(SystemTime::now());
-
-        app.mpsc_sender.send(InterTaskMessageToNetworkTask::ContactLLM {
-            msg: PlayerMessage {
-                msg: "Schreibe einen kurzen Konservationsstarter, lasse dir nicht anmerken, dass du eine KI bist. Reagiere nicht auf diese Nachricht. Schreibe nur etwas wie Hey! oder Hey, wie gehts dir? oder Hallo oder Moin oder ähnliches".to_string(),
-                from_ai: false,
-                to_ai: false,
-                timestamp: SystemTime::now(),
-            },
-            history: app.llm_history.clone(),
-            client: app.reqwest_client.clone(),
-            settings: app.settings.clone(),
-            lower_delay_limit: app.chars_per_second_lower,
-            upper_delay_limit: app.chars_per_second_upper,
-        }).expect("Channel to network task is closed :(");
-    }
-
-    if let Some(last_msg_time) = app.llm_last_message_time {
-        // let the llm send a message if the other player has not sent a message after a certain time
-        if last_msg_time.elapsed().unwrap().as_secs() >= app.llm_noresponse_iniative_time as u64 {
-            println!("No response from other player, sending message");
-            app.mpsc_sender.send(InterTaskMessageToNetworkTask::ContactLLM {
-                msg: PlayerMessage {
-                    msg: "Dein Gesprächspartner hat jetzt länger nicht geantwortet. Reagiere nicht auf diese Nachricht, sondern auf die Nachricht davor. Schreibe eine kurze Nachfrage wie Hallo?; Noch da?; ?.".to_string(),
-                    from_ai: false,
-                    to_ai: false,
-                    timestamp: SystemTime::now(),
-                },
-                history: app.llm_history.clone(),
-                client: app.reqwest_client.clone(),
-                settings: app.settings.clone(),
-                lower_delay_limit: app.chars_per_second_lower,
-                upper_delay_limit: app.chars_per_second_upper,
-            }).expect("Channel to network task is closed :(");
-            app.llm_last_message_time = Some(SystemTime::now());
-        }
-    }
-
     egui::CentralPanel::default().show(ctx, |ui| {
         ctx.request_repaint_after(std::time::Duration::from_secs(1));
         let total_width = ui.available_width();
@@ -69,7 +30,7 @@ pub fn render_game_screen(app: &mut ApplicationState, ctx: &Context, frame: &mut
                 strip.cell(|ui| {
                     ui.vertical_centered(|ui| {
                         ui.add_space(20.0);
-                        ui.heading("The Turing Challenge");
+                        ui.heading(app.text("title"));
                         ui.add_space(20.0);
                         StripBuilder::new(ui)
                             .size(Size::exact((content_width / 2.0) - 15.0))
@@ -80,7 +41,7 @@ pub fn render_game_screen(app: &mut ApplicationState, ctx: &Context, frame: &mut
                                     let mut frame = egui::Frame::default();
                                     frame = frame.inner_margin(Margin::same(5.0)).fill(Color32::from_hex("#29114C").unwrap());
                                     frame.show(ui, |ui| {
-                                        ui.label("Chat #1:");
+                                        ui.label(app.text("chat_1"));
                                         let history_space = ui.available_height() - 80.0;
                                         ScrollArea::vertical().stick_to_bottom(true).max_height(history_space).show(ui, |ui| {
                                             let max_width = ui.available_width();
@@ -116,7 +77,9 @@ pub fn render_game_screen(app: &mut ApplicationState, ctx: &Context, frame: &mut
                                                 timestamp: SystemTime::now(),
                                                 message: msg_text.clone(),
                                                 from: ChatMessageOrigin::Own,
+                                                annotation: None,
                                             });
+                                            spectator::publish_message(app, 0, true, &msg_text);
 
                                             let to_ai = if app.human_chat == 0 { //This is the chat to a human
                                                 false
@@ -130,6 +93,8 @@ pub fn render_game_screen(app: &mut ApplicationState, ctx: &Context, frame: &mut
                                                 from_ai: false,
                                                 to_ai,
                                                 timestamp: SystemTime::now(),
+                                                regenerations: 0,
+                                                filtered: false,
                                             });
 
                                             app.mpsc_sender.send(InterTaskMessageToNetworkTask::SendMsg { msg: tcp_msg }).expect("Channel to network task is closed :(");
@@ -138,7 +103,7 @@ pub fn render_game_screen(app: &mut ApplicationState, ctx: &Context, frame: &mut
                                                 if let Some(t) = app.last_message_time_own{
                                                     let time_elapsed = t.elapsed().unwrap().as_millis();
                                                     app.human_response_times_chars_per_second.push(msg_text.len() as f32 / time_elapsed as f32);
-                                                    println!("added own response time: {}", msg_text.len() as f32 / time_elapsed as f32);
+                                                    debug!("added own response time: {}", msg_text.len() as f32 / time_elapsed as f32);
                                                     app.last_message_time_own = Some(SystemTime::now());
                                                 }else{
                                                     app.last_message_time_own = Some(SystemTime::now());
@@ -156,7 +121,7 @@ pub fn render_game_screen(app: &mut ApplicationState, ctx: &Context, frame: &mut
                                     let mut frame = egui::Frame::default();
                                     frame = frame.inner_margin(Margin::same(5.0)).fill(Color32::from_hex("#29114C").unwrap());
                                     frame.show(ui, |ui| {
-                                        ui.label("Chat #2:");
+                                        ui.label(app.text("chat_2"));
                                         let history_space = ui.available_height() - 80.0;
 
                                         ScrollArea::vertical().stick_to_bottom(true).max_height(history_space).show(ui, |ui| {
@@ -193,7 +158,9 @@ pub fn render_game_screen(app: &mut ApplicationState, ctx: &Context, frame: &mut
                                                 timestamp: SystemTime::now(),
                                                 message: msg_text.clone(),
                                                 from: ChatMessageOrigin::Own,
+                                                annotation: None,
                                             });
+                                            spectator::publish_message(app, 1, true, &msg_text);
 
                                             let to_ai = if app.human_chat == 1 { //This is the chat to a human
                                                 false
@@ -207,6 +174,8 @@ pub fn render_game_screen(app: &mut ApplicationState, ctx: &Context, frame: &mut
                                                 timestamp: SystemTime::now(),
                                                 from_ai: false,
                                                 to_ai,
+                                                regenerations: 0,
+                                                filtered: false,
                                             });
                                             app.mpsc_sender.send(InterTaskMessageToNetworkTask::SendMsg { msg: tcp_msg }).expect("Channel to network task is closed :(");
 
@@ -214,7 +183,7 @@ pub fn render_game_screen(app: &mut ApplicationState, ctx: &Context, frame: &mut
                                                 if let Some(t) = app.last_message_time_own{
                                                     let time_elapsed = t.elapsed().unwrap().as_millis();
                                                     app.human_response_times_chars_per_second.push(msg_text.len() as f32 / time_elapsed as f32);
-                                                    println!("added own response time: {}", msg_text.len() as f32 / time_elapsed as f32);
+                                                    debug!("added own response time: {}", msg_text.len() as f32 / time_elapsed as f32);
                                                     app.last_message_time_own = Some(SystemTime::now());
                                                 }else{
                                                     app.last_message_time_own = Some(SystemTime::now());
@@ -0,0 +1,26 @@
+use eframe::egui::{Context, ViewportBuilder, ViewportId};
+use eframe::Frame;
+use crate::ApplicationState;
+
+/// Runs two player sessions in one process. Player 1 uses the main window, player 2 gets a second
+/// viewport so it can be moved to another monitor. Both sessions talk to each other through their
+/// own network tasks over loopback, exactly like two separate booths.
+pub struct HotSeatApp {
+    pub player1: ApplicationState,
+    pub player2: ApplicationState,
+}
+
+impl eframe::App for HotSeatApp {
+    fn update(&mut self, ctx: &Context, frame: &mut Frame) {
+        self.player1.update(ctx, frame);
+
+        let player2 = &mut self.player2;
+        ctx.show_viewport_immediate(
+            ViewportId::from_hash_of("hot_seat_player2"),
+            ViewportBuilder::default().with_title("The Turing Challenge - Player 2"),
+            |ctx, _class| {
+                player2.update(ctx, frame);
+            },
+        );
+    }
+}
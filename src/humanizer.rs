@@ -0,0 +1,250 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::settings::Settings;
+
+/// Rows of a German QWERTZ keyboard, used to find neighbouring keys for typos
+const KEYBOARD_ROWS: [&str; 3] = ["qwertzuiopü", "asdfghjklöä", "yxcvbnm"];
+
+/// Makes LLM replies look typed by a human: keyboard typos, dropped capitalization, replies split
+/// into several messages and the occasional "*correction" afterwards.
+/// Seeded with `humanizer_seed` the output is fully reproducible.
+#[derive(Debug)]
+pub struct Humanizer {
+    rng: StdRng,
+    /// Probability for each word to contain a typo
+    typo_rate: f64,
+    /// Probability for a reply to be written in lowercase only
+    lowercase_rate: f64,
+    /// Probability for a reply with several sentences to be sent as several messages
+    split_rate: f64,
+    /// Probability for a typo to be corrected in a follow-up message
+    correction_rate: f64,
+    /// Factor for all rates above, set by the difficulty
+    intensity: f64,
+}
+
+impl Humanizer {
+    pub fn new(settings: &Settings) -> Self {
+        let rng = match settings.humanizer_seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        Humanizer {
+            rng,
+            typo_rate: settings.humanizer_typo_rate,
+            lowercase_rate: settings.humanizer_lowercase_rate,
+            split_rate: settings.humanizer_split_rate,
+            correction_rate: settings.humanizer_correction_rate,
+            intensity: 1.0,
+        }
+    }
+
+    /// Take over changed rates from the settings. The random generator and the intensity of the
+    /// running round are kept, a new `humanizer_seed` only applies after a restart.
+    pub fn update_rates(&mut self, settings: &Settings) {
+        self.typo_rate = settings.humanizer_typo_rate;
+        self.lowercase_rate = settings.humanizer_lowercase_rate;
+        self.split_rate = settings.humanizer_split_rate;
+        self.correction_rate = settings.humanizer_correction_rate;
+    }
+
+    pub fn set_typo_rate(&mut self, typo_rate: f64) {
+        self.typo_rate = typo_rate;
+    }
+
+    pub fn set_intensity(&mut self, intensity: f64) {
+        self.intensity = intensity;
+    }
+
+    /// Roll the dice for a rate, scaled by the intensity
+    fn happens(&mut self, rate: f64) -> bool {
+        self.rng.gen_bool((rate * self.intensity).clamp(0.0, 1.0))
+    }
+
+    /// Turn an LLM reply into the messages which should be sent, in order
+    pub fn humanize(&mut self, reply: &str) -> Vec<String> {
+        let mut text = reply.trim().to_string();
+
+        if self.happens(self.lowercase_rate) {
+            text = text.to_lowercase();
+        }
+
+        let parts = if self.happens(self.split_rate) {
+            split_sentences(&text)
+        } else {
+            vec![text]
+        };
+
+        let mut messages = vec![];
+        for part in parts {
+            let (part, corrected_word) = self.add_typos(&part);
+            messages.push(part);
+            if let Some(word) = corrected_word {
+                messages.push(format!("*{}", word));
+            }
+        }
+
+        messages.retain(|msg| !msg.trim().is_empty());
+        messages
+    }
+
+    /// Add typos to some words. Returns the new text and, if the first typo should be corrected,
+    /// the original word.
+    fn add_typos(&mut self, text: &str) -> (String, Option<String>) {
+        let mut corrected_word = None;
+        let mut typo_made = false;
+
+        let words: Vec<String> = text.split(' ').map(|word| {
+            if word.chars().filter(|c| c.is_alphabetic()).count() < 3 || !self.happens(self.typo_rate) {
+                return word.to_string();
+            }
+
+            let Some(typo) = self.typo(word) else {
+                return word.to_string();
+            };
+
+            if !typo_made && self.happens(self.correction_rate) {
+                corrected_word = Some(word.trim_matches(|c: char| !c.is_alphanumeric()).to_string());
+            }
+            typo_made = true;
+            typo
+        }).collect();
+
+        (words.join(" "), corrected_word)
+    }
+
+    /// Hit a neighbouring key or swap two letters
+    fn typo(&mut self, word: &str) -> Option<String> {
+        let mut chars: Vec<char> = word.chars().collect();
+        let letter_positions: Vec<usize> = chars.iter().enumerate()
+            .filter(|(_, c)| c.is_alphabetic())
+            .map(|(i, _)| i)
+            .collect();
+
+        // Typos in the first letter are rare
+        let pos = letter_positions[self.rng.gen_range(1..letter_positions.len())];
+
+        if self.rng.gen_bool(0.3) && pos + 1 < chars.len() && chars[pos + 1].is_alphabetic() && chars[pos] != chars[pos + 1] {
+            chars.swap(pos, pos + 1);
+            return Some(chars.into_iter().collect());
+        }
+
+        let neighbours = keyboard_neighbours(chars[pos]);
+        if neighbours.is_empty() {
+            return None;
+        }
+        let replacement = neighbours[self.rng.gen_range(0..neighbours.len())];
+        chars[pos] = if chars[pos].is_uppercase() { replacement.to_uppercase().next().unwrap_or(replacement) } else { replacement };
+
+        Some(chars.into_iter().collect())
+    }
+}
+
+/// Keys left, right, above and below the given one
+fn keyboard_neighbours(c: char) -> Vec<char> {
+    let c = c.to_lowercase().next().unwrap_or(c);
+    let rows: Vec<Vec<char>> = KEYBOARD_ROWS.iter().map(|row| row.chars().collect()).collect();
+
+    let Some((row, col)) = rows.iter().enumerate()
+        .find_map(|(r, keys)| keys.iter().position(|k| *k == c).map(|col| (r, col))) else {
+        return vec![];
+    };
+
+    let mut neighbours = vec![];
+    if col > 0 {
+        neighbours.push(rows[row][col - 1]);
+    }
+    if let Some(k) = rows[row].get(col + 1) {
+        neighbours.push(*k);
+    }
+    if row > 0 {
+        if let Some(k) = rows[row - 1].get(col) {
+            neighbours.push(*k);
+        }
+    }
+    if let Some(k) = rows.get(row + 1).and_then(|r| r.get(col)) {
+        neighbours.push(*k);
+    }
+    neighbours
+}
+
+/// Split a text after sentence endings and line breaks
+fn split_sentences(text: &str) -> Vec<String> {
+    let mut parts = vec![];
+    let mut current = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            parts.push(std::mem::take(&mut current));
+            continue;
+        }
+        current.push(c);
+        if matches!(c, '.' | '!' | '?') && chars.peek().is_some_and(|next| next.is_whitespace()) {
+            parts.push(std::mem::take(&mut current));
+        }
+    }
+    parts.push(current);
+
+    parts.into_iter()
+        .map(|part| part.trim().to_string())
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPLY: &str = "Hey there, how are you doing today? I just came back from the lake. It was really nice!";
+
+    fn settings(seed: u64, rate: f64) -> Settings {
+        Settings {
+            humanizer_seed: Some(seed),
+            humanizer_typo_rate: rate,
+            humanizer_lowercase_rate: rate,
+            humanizer_split_rate: rate,
+            humanizer_correction_rate: rate,
+            ..Settings::default()
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_same_messages() {
+        for seed in 0..20 {
+            let mut first = Humanizer::new(&settings(seed, 0.5));
+            let mut second = Humanizer::new(&settings(seed, 0.5));
+            for _ in 0..5 {
+                assert_eq!(first.humanize(REPLY), second.humanize(REPLY));
+            }
+        }
+    }
+
+    #[test]
+    fn zero_rates_keep_the_reply() {
+        let mut humanizer = Humanizer::new(&settings(1, 0.0));
+        assert_eq!(humanizer.humanize(REPLY), vec![REPLY.to_string()]);
+    }
+
+    #[test]
+    fn full_rates_split_and_lowercase() {
+        let mut humanizer = Humanizer::new(&settings(1, 0.0));
+        humanizer.update_rates(&Settings { humanizer_lowercase_rate: 1.0, humanizer_split_rate: 1.0, ..settings(1, 0.0) });
+        assert_eq!(humanizer.humanize(REPLY), vec![
+            "hey there, how are you doing today?".to_string(),
+            "i just came back from the lake.".to_string(),
+            "it was really nice!".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn typos_keep_word_count() {
+        let mut humanizer = Humanizer::new(&settings(7, 1.0));
+        for message in humanizer.humanize("Something entirely different") {
+            if !message.starts_with('*') {
+                assert_eq!(message.split(' ').count(), 3, "{}", message);
+            }
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use config::{Config, File};
+use serde::Deserialize;
+use tracing::{warn};
+use crate::difficulty::Difficulty;
+use crate::settings::Settings;
+
+/// UI strings and LLM prompts of one language, loaded from `<i18n_dir>/<language code>.toml`
+#[derive(Debug, Deserialize, Clone)]
+pub struct Catalog {
+    /// Name shown in the language picker
+    pub name: String,
+    #[serde(default)]
+    pub prompts: CatalogPrompts,
+    #[serde(default)]
+    pub strings: HashMap<String, String>,
+}
+
+/// Prompts for the LLM in one language. Missing ones fall back to the prompts of the settings.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct CatalogPrompts {
+    pub initial_prompt: Option<String>,
+    pub take_initiative_prompt: Option<String>,
+    pub no_response_prompt: Option<String>,
+    pub custom_prompt_template: Option<String>,
+    pub output_filter_regenerate_prompt: Option<String>,
+    pub output_filter_fallback_replies: Option<Vec<String>>,
+    pub solo_opponent_prompt: Option<String>,
+    /// Strictness prompts by difficulty name, replacing the `strictness_prompt` of the difficulty
+    #[serde(default)]
+    pub strictness_prompts: HashMap<String, String>,
+}
+
+/// All loaded catalogs. Strings missing in a language are taken from the default language, if
+/// they're missing there too the key itself is shown.
+/// `{event_name}` and `{support_contact}` are replaced in all strings and prompts.
+#[derive(Debug, Clone)]
+pub struct Localization {
+    catalogs: BTreeMap<String, Catalog>,
+    default_language: String,
+    event_name: String,
+    support_contact: String,
+    initial_prompt: String,
+    take_initiative_prompt: String,
+    no_response_prompt: String,
+    custom_prompt_template: String,
+    regenerate_prompt: String,
+    fallback_replies: Vec<String>,
+    solo_opponent_prompt: String,
+}
+
+impl Localization {
+    pub fn load(settings: &Settings) -> Self {
+        Localization {
+            catalogs: load_catalogs(&settings.i18n_dir),
+            default_language: settings.default_language.clone(),
+            event_name: settings.event_name.clone(),
+            support_contact: settings.support_contact.clone(),
+            initial_prompt: settings.initial_prompt.clone(),
+            take_initiative_prompt: settings.llm_take_initiative_prompt.clone(),
+            no_response_prompt: settings.llm_no_response_prompt.clone(),
+            custom_prompt_template: settings.custom_prompt_template.clone(),
+            regenerate_prompt: settings.output_filter_regenerate_prompt.clone(),
+            fallback_replies: settings.output_filter_fallback_replies.clone(),
+            solo_opponent_prompt: settings.solo_opponent_prompt.clone(),
+        }
+    }
+
+    pub fn default_language(&self) -> &str {
+        &self.default_language
+    }
+
+    /// Codes and names of all available languages, sorted by code
+    pub fn languages(&self) -> Vec<(String, String)> {
+        self.catalogs.iter().map(|(code, catalog)| (code.clone(), catalog.name.clone())).collect()
+    }
+
+    /// Name of the language, or its code if there is no catalog for it
+    pub fn language_name(&self, language: &str) -> String {
+        self.catalogs.get(language).map(|catalog| catalog.name.clone()).unwrap_or_else(|| language.to_string())
+    }
+
+    /// UI string for `key` in the given language
+    pub fn text(&self, language: &str, key: &str) -> String {
+        let text = [language, self.default_language.as_str()].iter()
+            .find_map(|language| self.catalogs.get(*language).and_then(|catalog| catalog.strings.get(key)))
+            .map(|text| text.as_str())
+            .unwrap_or(key);
+        self.fill_in(text)
+    }
+
+    /// UI string for `key` with `{name}` placeholders replaced by the given values
+    pub fn format(&self, language: &str, key: &str, args: &[(&str, String)]) -> String {
+        args.iter().fold(self.text(language, key), |text, (name, value)| text.replace(&format!("{{{}}}", name), value))
+    }
+
+    /// Initial prompt for a bot chatting in the given language
+    pub fn initial_prompt(&self, language: &str) -> String {
+        self.prompt(language, |prompts| &prompts.initial_prompt, &self.initial_prompt)
+    }
+
+    /// Steering prompt asking the LLM to open the conversation in the given language
+    pub fn take_initiative_prompt(&self, language: &str) -> String {
+        self.prompt(language, |prompts| &prompts.take_initiative_prompt, &self.take_initiative_prompt)
+    }
+
+    /// Steering prompt asking the LLM to follow up in the given language
+    pub fn no_response_prompt(&self, language: &str) -> String {
+        self.prompt(language, |prompts| &prompts.no_response_prompt, &self.no_response_prompt)
+    }
+
+    /// Template the custom prompt is embedded in for a bot chatting in the given language
+    pub fn custom_prompt_template(&self, language: &str) -> String {
+        self.prompt(language, |prompts| &prompts.custom_prompt_template, &self.custom_prompt_template)
+    }
+
+    /// Hint for the LLM when the output filter rejected its reply
+    pub fn regenerate_prompt(&self, language: &str) -> String {
+        self.prompt(language, |prompts| &prompts.output_filter_regenerate_prompt, &self.regenerate_prompt)
+    }
+
+    /// Inconspicuous replies used when the LLM keeps giving itself away
+    pub fn fallback_replies(&self, language: &str) -> Vec<String> {
+        self.catalogs.get(language)
+            .and_then(|catalog| catalog.prompts.output_filter_fallback_replies.as_ref())
+            .unwrap_or(&self.fallback_replies)
+            .iter()
+            .map(|reply| self.fill_in(reply))
+            .collect()
+    }
+
+    /// Prompt for the LLM playing the human in solo mode
+    pub fn solo_opponent_prompt(&self, language: &str) -> String {
+        self.prompt(language, |prompts| &prompts.solo_opponent_prompt, &self.solo_opponent_prompt)
+    }
+
+    /// Strictness prompt of the difficulty in the given language, empty if there is none
+    pub fn strictness_prompt(&self, language: &str, difficulty: &Difficulty) -> String {
+        let prompt = self.catalogs.get(language)
+            .and_then(|catalog| catalog.prompts.strictness_prompts.get(&difficulty.name))
+            .unwrap_or(&difficulty.strictness_prompt);
+        self.fill_in(prompt)
+    }
+
+    fn prompt(&self, language: &str, select: impl Fn(&CatalogPrompts) -> &Option<String>, fallback: &str) -> String {
+        let prompt = self.catalogs.get(language)
+            .and_then(|catalog| select(&catalog.prompts).as_deref())
+            .unwrap_or(fallback);
+        self.fill_in(prompt)
+    }
+
+    /// Replace the placeholders for event name and support contact
+    pub fn fill_in(&self, text: &str) -> String {
+        text.replace("{event_name}", &self.event_name)
+            .replace("{support_contact}", &self.support_contact)
+    }
+}
+
+/// Load all catalogs (TOML or JSON files) from the given directory, keyed by file name
+fn load_catalogs(dir: &str) -> BTreeMap<String, Catalog> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Failed to read i18n directory: {}", e);
+            return BTreeMap::new();
+        }
+    };
+
+    let mut catalogs = BTreeMap::new();
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.extension().is_some_and(|ext| ext == "toml" || ext == "json") {
+            continue;
+        }
+        let Some(language) = path.file_stem().map(|stem| stem.to_string_lossy().to_string()) else {
+            continue;
+        };
+
+        let catalog = Config::builder()
+            .add_source(File::from(path.as_path()))
+            .build()
+            .and_then(|c| c.try_deserialize::<Catalog>());
+
+        match catalog {
+            Ok(catalog) => {
+                catalogs.insert(language, catalog);
+            },
+            Err(e) => {
+                warn!("Failed to load i18n catalog {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    catalogs
+}
@@ -0,0 +1,69 @@
+use std::time::{Duration, SystemTime};
+use eframe::egui::{self, Align2, Context, Event, RichText};
+use tracing::{info};
+use crate::{reset_app_state, save_human_response_times_to_file, ApplicationState, InterTaskMessageToNetworkTask, Screen, TcpMessage};
+
+/// Whether the player touched the booth since the last frame
+pub fn user_input(ctx: &Context) -> bool {
+    ctx.input(|i| i.events.iter().any(|event| matches!(event,
+        Event::Key { .. } | Event::Text(_) | Event::PointerButton { .. } | Event::PointerMoved(_) | Event::MouseWheel { .. } | Event::Touch { .. }
+    )))
+}
+
+/// Phases in which a player walking away blocks the opponent or keeps the booth from returning to the start
+fn watched(screen: &Screen) -> bool {
+    matches!(screen, Screen::Welcome | Screen::Prompting | Screen::Game | Screen::End | Screen::Reveal)
+}
+
+/// Ask an idle player whether they're still there and abandon the round if the countdown runs out.
+/// Disabled if `idle_timeout_secs` is 0.
+pub fn check_idle(app: &mut ApplicationState, ctx: &Context) {
+    if app.settings.idle_timeout_secs == 0 || !watched(&app.screen) {
+        return;
+    }
+
+    let idle_secs = app.last_input_time.elapsed().map_or(0, |idle| idle.as_secs());
+    if idle_secs < app.settings.idle_timeout_secs {
+        return;
+    }
+
+    let countdown_end = app.settings.idle_timeout_secs + app.settings.idle_countdown_secs;
+    if idle_secs >= countdown_end {
+        info!("Player was idle for {} seconds, abandoning the round", idle_secs);
+        abandon_round(app);
+        return;
+    }
+
+    let remaining = countdown_end - idle_secs;
+    egui::Window::new("still_there")
+        .title_bar(false)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.heading(app.text("still_there_heading"));
+                ui.add_space(10.0);
+                ui.label(RichText::new(app.localization.format(&app.language, "still_there_countdown", &[("secs", remaining.to_string())])).size(24.0));
+                ui.add_space(10.0);
+                if ui.button(app.text("still_there_button")).clicked() {
+                    app.last_input_time = SystemTime::now();
+                }
+            });
+        });
+}
+
+/// Tell the opponent we're gone and go back to the start screen
+fn abandon_round(app: &mut ApplicationState) {
+    app.mpsc_sender.send(InterTaskMessageToNetworkTask::SendMsg { msg: TcpMessage::Abandoned }).expect("Channel to network task is closed :(");
+
+    save_human_response_times_to_file(&app.human_response_times_chars_per_second);
+    reset_app_state(app);
+
+    // Give the writer a moment to send the message before the connection is closed
+    let restart_sender = app.mpsc_restart_sender.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let _ = restart_sender.send(());
+    });
+}
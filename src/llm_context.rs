@@ -0,0 +1,144 @@
+use crate::prompt_library::estimate_tokens;
+use crate::LLMMessage;
+
+/// Start of the developer message which replaces trimmed conversation turns
+const SUMMARY_PREFIX: &str = "Summary of the earlier conversation (shortened):";
+/// Maximum length of a single turn in the summary, in characters
+const SUMMARY_LINE_CHARS: usize = 80;
+/// Number of most recent turns which are never trimmed
+const KEEP_RECENT_TURNS: usize = 4;
+
+/// Rough token estimate for a whole conversation
+pub(crate) fn estimate_history_tokens(history: &[LLMMessage]) -> usize {
+    // Every message has a few tokens of overhead for role & separators
+    history.iter().map(|msg| estimate_tokens(&msg.content) + 4).sum()
+}
+
+/// Trim the oldest conversation turns until the history fits into `budget` tokens.
+/// The prompt layers at the beginning are always kept, trimmed turns are condensed into a short
+/// summary message right after them. A budget of 0 disables trimming.
+pub(crate) fn trim_history(history: &mut Vec<LLMMessage>, budget: usize) {
+    if budget == 0 || estimate_history_tokens(history) <= budget {
+        return;
+    }
+
+    let pinned = history.iter()
+        .take_while(|msg| msg.role == "developer" && !is_summary(msg))
+        .count();
+
+    let mut summary_lines: Vec<String> = match history.get(pinned) {
+        Some(msg) if is_summary(msg) => {
+            let summary = history.remove(pinned);
+            summary.content.lines().skip(1).map(|line| line.to_string()).collect()
+        },
+        _ => vec![],
+    };
+
+    while estimate_history_tokens(history) + estimate_summary_tokens(&summary_lines) > budget && history.len() > pinned + KEEP_RECENT_TURNS {
+        let turn = history.remove(pinned);
+        summary_lines.push(summarize_turn(&turn));
+
+        // The summary must not eat up the budget itself
+        while summary_lines.len() > 1 && estimate_summary_tokens(&summary_lines) > budget / 4 {
+            summary_lines.remove(0);
+        }
+    }
+
+    if !summary_lines.is_empty() {
+        history.insert(pinned, LLMMessage {
+            role: "developer".to_string(),
+            content: format!("{}\n{}", SUMMARY_PREFIX, summary_lines.join("\n")),
+            refusal: None,
+        });
+    }
+}
+
+fn is_summary(msg: &LLMMessage) -> bool {
+    msg.role == "developer" && msg.content.starts_with(SUMMARY_PREFIX)
+}
+
+fn estimate_summary_tokens(lines: &[String]) -> usize {
+    if lines.is_empty() {
+        return 0;
+    }
+    estimate_tokens(SUMMARY_PREFIX) + lines.iter().map(|line| estimate_tokens(line) + 1).sum::<usize>() + 4
+}
+
+fn summarize_turn(turn: &LLMMessage) -> String {
+    let speaker = match turn.role.as_str() {
+        "user" => "Opponent",
+        "assistant" => "You",
+        _ => "Note",
+    };
+
+    let content = turn.content.replace('\n', " ");
+    if content.chars().count() > SUMMARY_LINE_CHARS {
+        format!("{}: {}...", speaker, content.chars().take(SUMMARY_LINE_CHARS).collect::<String>())
+    } else {
+        format!("{}: {}", speaker, content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: &str) -> LLMMessage {
+        LLMMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            refusal: None,
+        }
+    }
+
+    fn conversation(turns: usize) -> Vec<LLMMessage> {
+        let mut history = vec![message("developer", "Imitate a human."), message("developer", "Be a pirate.")];
+        for i in 0..turns {
+            let role = if i % 2 == 0 { "user" } else { "assistant" };
+            history.push(message(role, &format!("Turn number {} of a conversation that goes on and on and on", i)));
+        }
+        history
+    }
+
+    fn contents(history: &[LLMMessage]) -> Vec<String> {
+        history.iter().map(|msg| msg.content.clone()).collect()
+    }
+
+    #[test]
+    fn history_within_budget_is_untouched() {
+        let mut history = conversation(10);
+        let before = contents(&history);
+        trim_history(&mut history, 0);
+        assert_eq!(contents(&history), before);
+        let budget = estimate_history_tokens(&history);
+        trim_history(&mut history, budget);
+        assert_eq!(contents(&history), before);
+    }
+
+    #[test]
+    fn trimming_keeps_the_system_prompts_and_recent_turns() {
+        let mut history = conversation(30);
+        let before = contents(&history);
+        trim_history(&mut history, 150);
+
+        assert_eq!(contents(&history[..2]), before[..2]);
+        assert!(is_summary(&history[2]));
+        assert_eq!(contents(&history[history.len() - KEEP_RECENT_TURNS..]), before[before.len() - KEEP_RECENT_TURNS..]);
+        assert!(history.len() < before.len());
+    }
+
+    #[test]
+    fn repeated_trimming_keeps_a_single_summary() {
+        let mut history = conversation(30);
+        trim_history(&mut history, 150);
+        for i in 0..10 {
+            history.push(message("user", &format!("Another message {} which is long enough to matter", i)));
+            trim_history(&mut history, 150);
+        }
+
+        assert_eq!(history.iter().filter(|msg| is_summary(msg)).count(), 1);
+        assert_eq!(history[0].content, "Imitate a human.");
+        assert_eq!(history[1].content, "Be a pirate.");
+        assert!(is_summary(&history[2]));
+    }
+}
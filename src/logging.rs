@@ -0,0 +1,48 @@
+use serde::Deserialize;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer};
+use crate::settings::Settings;
+
+/// Target of all log events containing chat messages or prompts, disabled unless `log_chat_content` is set
+pub const CHAT: &str = "chat";
+
+/// How often a new log file is started
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+/// Log to stdout and, if `log_dir` is set, to rotated files in it. `RUST_LOG` overrides `log_level`.
+/// The returned guard flushes the file log when dropped, keep it until the end of `main`.
+pub fn init(settings: &Settings) -> Option<WorkerGuard> {
+    let level = std::env::var("RUST_LOG").unwrap_or_else(|_| settings.log_level.clone());
+    let chat = if settings.log_chat_content { "debug" } else { "off" };
+    let filter = || EnvFilter::try_new(format!("{},{}={}", level, CHAT, chat)).unwrap_or_else(|e| {
+        eprintln!("Invalid log level {}: {}", level, e);
+        EnvFilter::new(format!("info,{}={}", CHAT, chat))
+    });
+
+    let stdout = fmt::layer().with_filter(filter());
+
+    if settings.log_dir.is_empty() {
+        tracing_subscriber::registry().with(stdout).init();
+        return None;
+    }
+
+    let rotation = match settings.log_rotation {
+        LogRotation::Hourly => Rotation::HOURLY,
+        LogRotation::Daily => Rotation::DAILY,
+        LogRotation::Never => Rotation::NEVER,
+    };
+    let (file, guard) = tracing_appender::non_blocking(RollingFileAppender::new(rotation, &settings.log_dir, "turing-challenge.log"));
+    let file = fmt::layer().with_ansi(false).with_writer(file).with_filter(filter());
+    tracing_subscriber::registry().with(stdout).with(file).init();
+    Some(guard)
+}
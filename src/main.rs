@@ -4,33 +4,67 @@ use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 use bincode::{Decode, Encode};
-use eframe::egui::{Context, FontData, FontDefinitions, FontFamily, FontId, TextStyle};
+use clap::Parser;
+use eframe::egui::{Context, FontData, FontDefinitions, FontFamily, FontId, RichText, TextStyle};
 use eframe::{egui, Frame};
 use rand::Rng;
 use reqwest::Client;
 use serde::{Deserialize, Serialize, Serializer};
 use serde::ser::SerializeMap;
 use tokio::sync::broadcast;
-use crate::settings::Settings;
+use tracing::{debug, error, info, info_span, warn};
+use crate::i18n::Localization;
+use crate::difficulty::Difficulty;
+use crate::persona::Persona;
+use crate::settings::{Mode, Settings, Transport};
+use crate::logging::CHAT;
 
 pub mod certs;
+pub mod cli;
 pub mod settings;
 pub mod network;
+pub mod bot;
 pub mod start_screen;
+pub mod attract_screen;
+pub mod idle;
 pub mod welcome_screen;
 pub mod prompting_screen;
 pub mod game_screen;
 pub mod end_screen;
+pub mod reveal_screen;
+pub mod hot_seat;
+pub mod metrics;
+pub mod solo;
+pub mod spectator;
+pub mod prompt_library;
+pub mod moderation;
+pub mod output_filter;
+pub mod humanizer;
+pub mod i18n;
+pub mod persona;
+pub mod difficulty;
+pub mod admin;
+pub mod rounds;
+pub mod secret;
+pub mod stats;
+pub mod llm_context;
+pub mod logging;
+pub mod transcripts;
 
 #[derive(Debug, Clone, Default)]
 pub enum Screen {
     #[default]
     Start,
+    /// Shown on the idle booth instead of the start screen
+    Attract,
     Welcome,
     Prompting,
     Game,
     End,
-    End2,
+    /// Shows which chat was the AI after the guess
+    Reveal,
+    /// Statistics over all rounds, opened from the admin panel
+    Stats,
 }
 
 #[derive(Debug)]
@@ -39,6 +73,13 @@ pub struct ApplicationState {
     pub screen: Screen,
     pub name: String,
     pub custom_prompt: String,
+    pub prompt_library: Vec<prompt_library::PromptCategory>,
+    pub selected_prompt_category: usize,
+    /// Category of the last template inserted into the custom prompt
+    pub custom_prompt_category: Option<String>,
+    /// Waiting for the moderation endpoint to check the custom prompt
+    pub prompt_moderation_pending: bool,
+    pub prompt_moderation_warning: Option<String>,
     pub warning: Option<String>,
     pub marked_as_ready: bool,
     pub marked_as_ready_opponent: bool,
@@ -53,18 +94,60 @@ pub struct ApplicationState {
     pub human_chat: u8,
     pub prompting_start_time: Option<SystemTime>,
     pub game_start_time: Option<SystemTime>,
-    pub llm_history: Vec<LLMMessage>,
-    pub llm_take_iniative_after: u8,
-    pub llm_chat_first_message: bool,
-    pub llm_last_message_time: Option<SystemTime>,
-    pub llm_noresponse_iniative_time: u8,
+    pub humanizer: humanizer::Humanizer,
     pub correctly_guessed: Option<bool>,
     pub showing_end_screen_since: Option<SystemTime>,
     pub reqwest_client: Client,
     pub settings: Arc<settings::Settings>,
     pub mpsc_sender: tokio::sync::broadcast::Sender<InterTaskMessageToNetworkTask>,
     pub mpsc_receiver: tokio::sync::broadcast::Receiver<InterTaskMessageToGUI>,
+    /// Sender for results of background tasks spawned by the GUI
+    pub mpsc_gui_sender: tokio::sync::broadcast::Sender<InterTaskMessageToGUI>,
     pub mpsc_restart_sender: tokio::sync::broadcast::Sender<()>,
+    pub bot_sender: tokio::sync::broadcast::Sender<InterTaskMessageToBot>,
+    /// Events for the spectator feed, dropped if it's disabled
+    pub spectator_sender: tokio::sync::broadcast::Sender<spectator::SpectatorEvent>,
+    /// Phase last published to the spectators
+    pub spectator_phase: &'static str,
+    pub metrics: Arc<metrics::Metrics>,
+    /// Id of the round, used for the logs and the round record
+    pub round_id: String,
+    /// Span of the current round, from the connection to the reset
+    pub round_span: tracing::Span,
+    /// When the last message of the opponent was passed to the bot, for the reply delay metric
+    pub last_message_to_bot_time: Option<SystemTime>,
+    /// Task sending the remaining parts of a split bot reply, aborted when they'd come too late
+    pub bot_follow_ups: Option<tokio::task::AbortHandle>,
+    pub localization: Arc<Localization>,
+    /// Language picked by the player
+    pub language: String,
+    /// Language picked by the opponent, the bot chats with them in this language
+    pub opponent_language: Option<String>,
+    /// Persona our bot plays this round
+    pub persona: Option<Persona>,
+    /// Name of the persona the opponent's bot plays this round
+    pub opponent_persona: Option<String>,
+    /// Custom prompt of the opponent, which steered the bot the player chatted with
+    pub opponent_custom_prompt: Option<String>,
+    pub opponent_prompt_category: Option<String>,
+    /// Difficulty picked on the welcome screen, follows the opponent's pick until we're ready
+    pub difficulty: String,
+    /// Difficulty picked by the opponent
+    pub opponent_difficulty: Option<String>,
+    /// Difficulty both booths agreed on for this round
+    pub round_difficulty: Option<Difficulty>,
+    pub admin: admin::AdminPanel,
+    /// Chat the player picked as the AI chat on the end screen, not submitted yet
+    pub guessed_chat: Option<u8>,
+    /// How sure the player is about the guess, in percent
+    pub guess_confidence: u8,
+    /// What gave the AI away, according to the player
+    pub guess_reasoning: String,
+    /// Last time the player touched the booth
+    pub last_input_time: SystemTime,
+    pub attract: attract_screen::AttractContent,
+    /// Loaded when the stats screen is opened
+    pub stats: Option<stats::Dashboard>,
     pub last_message_time_own: Option<SystemTime>,
     pub last_message_time_foreign: Option<SystemTime>,
     pub chars_per_second_lower: f32,
@@ -77,6 +160,14 @@ pub struct ApplicationState {
 struct LLMResponseBundle{
     new_message_from_llm: Option<String>,
     history: Vec<LLMMessage>,
+    /// How often the reply was regenerated by the output filter
+    regenerations: u8,
+    /// Whether the output filter changed or replaced the reply
+    filtered: bool,
+    /// Response time of the last successful request
+    latency: Option<Duration>,
+    /// Error of the last failed request
+    error: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -113,8 +204,9 @@ struct LLMResponseChoice{
     message: LLMMessage,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-enum LLMModel{
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub enum LLMModel{
+    #[default]
     #[serde(rename = "chatgpt-4o-latest")]
     GPT4o,
     #[serde(rename = "o1")]
@@ -134,11 +226,16 @@ impl Display for LLMModel{
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct LLMMessage{
     role: String,
+    /// `null` in refusals, read as empty text
+    #[serde(default, deserialize_with = "null_as_empty")]
     content: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     refusal: Option<String>
 }
 
+fn null_as_empty<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?.unwrap_or_default())
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 enum LLMMessageRole{
@@ -162,7 +259,17 @@ impl Display for LLMMessageRole{
 struct ChatMessage{
     timestamp: SystemTime,
     message: String,
-    from: ChatMessageOrigin
+    from: ChatMessageOrigin,
+    /// Details about a message of the opponent's bot, shown on the reveal screen
+    annotation: Option<ReplyAnnotation>,
+}
+
+#[derive(Debug)]
+struct ReplyAnnotation{
+    /// Time between the player's last message and the reply
+    latency: Option<Duration>,
+    regenerations: u8,
+    filtered: bool,
 }
 
 #[derive(Debug)]
@@ -172,9 +279,9 @@ enum ChatMessageOrigin{
 }
 
 impl ApplicationState{
-    pub fn new(cc: &eframe::CreationContext<'_>, mpsc_sender: broadcast::Sender<InterTaskMessageToNetworkTask>, mpsc_receiver: broadcast::Receiver<InterTaskMessageToGUI>, mpsc_restart_sender: broadcast::Sender<()>) -> Self {
-        let settings = settings::Settings::new().expect("Failed to load settings");
-
+    pub fn new(cc: &eframe::CreationContext<'_>, session: Session) -> Self {
+        let settings = session.settings;
+        let localization = session.localization;
         let previous_human_response_times = load_previous_human_response_times();
 
         let mut fonts = FontDefinitions::default();
@@ -200,8 +307,6 @@ impl ApplicationState{
         // Generate randomly which foreign chat belongs to the real human
         let mut rng = rand::thread_rng();
         let human_chat : u8= rng.gen_range(0..=1);
-        let llm_noresponse_iniative_time = rng.gen_range(15..=30);
-        let llm_take_iniative_after = rng.gen_range(settings.llm_take_initiative_after_lower_bound..=settings.llm_take_initiative_after_upper_bound);
 
         let (chars_per_second_lower, chars_per_second_upper) = calculate_average_chars_per_second_limits(&previous_human_response_times);
 
@@ -210,6 +315,11 @@ impl ApplicationState{
             screen: Screen::Start,
             name: "".to_string(),
             custom_prompt: "".to_string(),
+            prompt_library: prompt_library::load_prompt_library(&settings.prompt_library_dir),
+            selected_prompt_category: 0,
+            custom_prompt_category: None,
+            prompt_moderation_pending: false,
+            prompt_moderation_warning: None,
             warning: None,
             marked_as_ready: false,
             marked_as_ready_opponent: false,
@@ -223,18 +333,40 @@ impl ApplicationState{
             human_chat,
             prompting_start_time: None,
             game_start_time: None,
-            llm_history: vec![],
-            llm_take_iniative_after: llm_take_iniative_after.try_into().unwrap(),
-            llm_chat_first_message: false,
-            llm_last_message_time: None,
-            llm_noresponse_iniative_time,
+            humanizer: humanizer::Humanizer::new(&settings),
             correctly_guessed: None,
             showing_end_screen_since: None,
             reqwest_client: Client::new(),
-            settings: Arc::new(settings),
-            mpsc_sender,
-            mpsc_receiver,
-            mpsc_restart_sender,
+            difficulty: settings.default_difficulty.clone(),
+            settings,
+            mpsc_sender: session.sender_to_network,
+            mpsc_receiver: session.receiver_from_network,
+            mpsc_gui_sender: session.sender_to_gui,
+            mpsc_restart_sender: session.restart_sender,
+            bot_sender: session.bot_sender,
+            spectator_sender: session.spectator_sender,
+            spectator_phase: "idle",
+            metrics: session.metrics,
+            round_id: "".to_string(),
+            round_span: tracing::Span::none(),
+            last_message_to_bot_time: None,
+            bot_follow_ups: None,
+            language: localization.default_language().to_string(),
+            opponent_language: None,
+            persona: None,
+            opponent_persona: None,
+            opponent_custom_prompt: None,
+            opponent_prompt_category: None,
+            opponent_difficulty: None,
+            round_difficulty: None,
+            admin: admin::AdminPanel::default(),
+            guessed_chat: None,
+            guess_confidence: 50,
+            guess_reasoning: "".to_string(),
+            last_input_time: SystemTime::now(),
+            attract: attract_screen::AttractContent::default(),
+            stats: None,
+            localization,
             human_response_times_chars_per_second: vec![],
             chars_per_second_upper,
             chars_per_second_lower,
@@ -242,25 +374,75 @@ impl ApplicationState{
             last_message_time_foreign: None,
         }
     }
+
+    /// UI string in the language of the player
+    pub fn text(&self, key: &str) -> String {
+        self.localization.text(&self.language, key)
+    }
+
+    /// Length of the chat phase of this round in seconds
+    pub fn game_duration_secs(&self) -> u64 {
+        self.round_difficulty.as_ref().map_or(difficulty::default_game_duration_secs(), |difficulty| difficulty.game_duration_secs)
+    }
+
+    /// Rules shown before the round, with the durations of the difficulty picked so far
+    pub fn start_rules(&self) -> String {
+        let game_duration_secs = self.round_difficulty.as_ref()
+            .or_else(|| self.settings.difficulties.iter().find(|difficulty| difficulty.name == self.difficulty))
+            .map_or(difficulty::default_game_duration_secs(), |difficulty| difficulty.game_duration_secs);
+        let decimal_separator = self.text("decimal_separator");
+        self.localization.format(&self.language, "start_rules", &[
+            ("game_minutes", format_minutes(game_duration_secs, &decimal_separator)),
+            ("prompting_minutes", format_minutes(prompting_screen::PROMPTING_DURATION_SECS, &decimal_separator)),
+        ])
+    }
+
+    /// Typing speed limits of the bot in chars per second, adjusted to its persona and the difficulty
+    pub fn bot_typing_speed(&self) -> (f32, f32) {
+        let multiplier = self.persona.as_ref().map_or(1.0, |persona| persona.typing_speed_multiplier)
+            * self.round_difficulty.as_ref().map_or(1.0, |difficulty| difficulty.typing_speed_multiplier);
+        (self.chars_per_second_lower * multiplier, self.chars_per_second_upper * multiplier)
+    }
+
+    /// Language the bot chats in: the opponent's one, or ours if they didn't tell us
+    pub fn bot_language(&self) -> String {
+        self.opponent_language.clone().unwrap_or_else(|| self.language.clone())
+    }
 }
 
 impl eframe::App for ApplicationState{
     fn update(&mut self, ctx: &Context, frame: &mut Frame) {
+        let round_span = self.round_span.clone();
+        let _round = round_span.enter();
         ctx.request_repaint_after(Duration::from_secs(1));
         if !self.mpsc_receiver.is_empty(){
             match self.mpsc_receiver.try_recv(){
                 Ok(msg) => {
-                    println!("Received message: {:?}", msg);
+                    debug!(target: CHAT, "Received message: {:?}", msg);
                     match msg {
                         InterTaskMessageToGUI::Connected { with } => {
+                            self.metrics.connected();
+                            self.round_id = uuid::Uuid::new_v4().to_string();
+                            self.round_span = info_span!("round", id = %self.round_id);
+                            self.round_span.in_scope(|| info!("Connected with {}", with));
+                            self.admin.peer = Some(with);
                             self.screen = Screen::Welcome;
                         },
                         InterTaskMessageToGUI::ConnectionFailed { error } => {
+                            self.metrics.connection_failed();
+                            self.admin.last_connection_error = Some(error.clone());
                             self.warning = Some(error);
                             self.start_game_pressed = false;
                         },
+                        InterTaskMessageToGUI::ConnectionClosedUnexpectedly {error} if chat_is_over(&self.screen) => {
+                            // The opponent finished the round first, let the player guess and see the reveal.
+                            // The network task is restarted when this booth finishes the round too.
+                            info!("Opponent left after the chat: {}", error);
+                        },
                         InterTaskMessageToGUI::ConnectionClosedUnexpectedly {error} => {
-                            println!("Connection closed unexpectedly: {}", error);
+                            warn!("Connection closed unexpectedly: {}", error);
+                            self.metrics.connection_dropped();
+                            self.admin.last_connection_error = Some(error);
                             // Save the human response times
                             save_human_response_times_to_file(&self.human_response_times_chars_per_second);
                             // Calculate the new average chars per second
@@ -284,39 +466,67 @@ impl eframe::App for ApplicationState{
                                 TcpMessage::MarkedAsReady => {
                                     self.marked_as_ready_opponent = true;
                                 },
+                                TcpMessage::LanguageSelected(language) => {
+                                    self.opponent_language = Some(language);
+                                },
+                                TcpMessage::Abandoned if chat_is_over(&self.screen) => {
+                                    info!("Opponent walked away after the chat, finishing the round here");
+                                },
+                                TcpMessage::Abandoned => {
+                                    info!("Opponent abandoned the round");
+                                    force_reset(self);
+                                    self.warning = Some(self.text("opponent_abandoned"));
+                                },
+                                TcpMessage::DifficultySelected(difficulty) => {
+                                    if !self.marked_as_ready{
+                                        self.difficulty = difficulty.clone();
+                                    }
+                                    self.opponent_difficulty = Some(difficulty);
+                                },
+                                TcpMessage::PersonaSelected(persona) => {
+                                    self.opponent_persona = Some(persona);
+                                },
+                                TcpMessage::CustomPrompt { prompt, category } => {
+                                    self.opponent_custom_prompt = Some(prompt);
+                                    self.opponent_prompt_category = category;
+                                },
                                 TcpMessage::PromptingFinished => {
                                     self.marked_as_prompt_ready_opponent = true;
                                 },
                                 TcpMessage::EndGame => {
-                                    self.screen = Screen::End;
+                                    end_chat(self);
                                 },
                                 TcpMessage::Message(player_message) => {
                                     if player_message.to_ai{
-                                        self.llm_last_message_time = Some(SystemTime::now());
-                                        self.llm_chat_first_message = true;
-                                        self.mpsc_sender.send(InterTaskMessageToNetworkTask::ContactLLM {
+                                        // The bot answers everything together, like it does with a request in flight
+                                        cancel_bot_follow_ups(self);
+                                        self.last_message_to_bot_time = Some(SystemTime::now());
+                                        self.bot_sender.send(InterTaskMessageToBot::OpponentMessage {
                                             msg: player_message,
-                                            history: self.llm_history.clone(),
-                                            client: self.reqwest_client.clone(),
-                                            settings: self.settings.clone(),
-                                            lower_delay_limit: self.chars_per_second_lower,
-                                            upper_delay_limit: self.chars_per_second_upper,
                                         }).unwrap();
                                     }else{
                                         if player_message.from_ai{
-                                            if self.human_chat == 0{
-                                                self.chat2_history.push(ChatMessage{
-                                                    timestamp: player_message.timestamp,
-                                                    message: player_message.msg,
-                                                    from: ChatMessageOrigin::Foreign
-                                                });
+                                            spectator::publish_message(self, 1 - self.human_chat, false, &player_message.msg);
+                                            let ai_chat_history = if self.human_chat == 0{
+                                                &mut self.chat2_history
                                             }else{
-                                                self.chat1_history.push(ChatMessage{
-                                                    timestamp: player_message.timestamp,
-                                                    message: player_message.msg,
-                                                    from: ChatMessageOrigin::Foreign
-                                                });
-                                            }
+                                                &mut self.chat1_history
+                                            };
+                                            // Only a reply to the player's last message has a latency
+                                            let latency = match ai_chat_history.last(){
+                                                Some(ChatMessage{ from: ChatMessageOrigin::Own, timestamp, .. }) => SystemTime::now().duration_since(*timestamp).ok(),
+                                                _ => None,
+                                            };
+                                            ai_chat_history.push(ChatMessage{
+                                                timestamp: player_message.timestamp,
+                                                message: player_message.msg,
+                                                from: ChatMessageOrigin::Foreign,
+                                                annotation: Some(ReplyAnnotation{
+                                                    latency,
+                                                    regenerations: player_message.regenerations,
+                                                    filtered: player_message.filtered,
+                                                }),
+                                            });
                                         }else{
                                             // Message not from AI, calculate response time
                                             match self.last_message_time_foreign{
@@ -325,7 +535,7 @@ impl eframe::App for ApplicationState{
                                                     let elapsed_secs = elapsed.as_millis();
 
                                                     let chars_per_second = player_message.msg.len() as f32 / elapsed_secs as f32;
-                                                    println!("Added foreign chars per second: {}", chars_per_second);
+                                                    debug!("Added foreign chars per second: {}", chars_per_second);
                                                     self.human_response_times_chars_per_second.push(chars_per_second);
 
                                                     self.last_message_time_foreign = Some(SystemTime::now());
@@ -334,17 +544,20 @@ impl eframe::App for ApplicationState{
                                                     self.last_message_time_foreign = Some(SystemTime::now());
                                                 }
                                             }
+                                            spectator::publish_message(self, self.human_chat, false, &player_message.msg);
                                             if self.human_chat == 0{
                                                 self.chat1_history.push(ChatMessage{
                                                     timestamp: player_message.timestamp,
                                                     message: player_message.msg,
-                                                    from: ChatMessageOrigin::Foreign
+                                                    from: ChatMessageOrigin::Foreign,
+                                                    annotation: None,
                                                 });
                                             }else{
                                                 self.chat2_history.push(ChatMessage{
                                                     timestamp: player_message.timestamp,
                                                     message: player_message.msg,
-                                                    from: ChatMessageOrigin::Foreign
+                                                    from: ChatMessageOrigin::Foreign,
+                                                    annotation: None,
                                                 });
                                             }
                                         }
@@ -354,29 +567,86 @@ impl eframe::App for ApplicationState{
                         }
                         InterTaskMessageToGUI::ListenForConnections => {}
                         InterTaskMessageToGUI::MspcSender { .. } => {}
-                        InterTaskMessageToGUI::HandleLLMResponse { response } => {
-                            self.llm_history = response.history;
-
-                            if let Some(new_msg) = response.new_message_from_llm {
-                                // Send the message to the opponent
-                                self.mpsc_sender.send(InterTaskMessageToNetworkTask::SendMsg {
-                                    msg: TcpMessage::Message(PlayerMessage {
-                                        msg: new_msg.clone(),
-                                        from_ai: true,
-                                        timestamp: SystemTime::now(),
-                                        to_ai: false,
-                                    })
-                                }).unwrap();
+                        InterTaskMessageToGUI::ModerationResult { flagged_categories, forced } => {
+                            prompting_screen::handle_moderation_result(self, flagged_categories, forced);
+                        }
+                        InterTaskMessageToGUI::LLMStatus { latency, error } => {
+                            self.metrics.llm_request(latency, error.is_some());
+                            self.admin.record_llm_status(latency, error);
+                        },
+                        InterTaskMessageToGUI::BotReply { .. } if !matches!(self.screen, Screen::Game) => {
+                            debug!("Dropped bot reply, the chat is over");
+                        },
+                        InterTaskMessageToGUI::BotReply { msg: new_msg, regenerations, filtered } => {
+                            if let Some(delay) = self.last_message_to_bot_time.take().and_then(|time| time.elapsed().ok()) {
+                                self.metrics.message_delay(delay);
+                            }
+                            if filtered {
+                                info!("LLM reply was filtered, {} regeneration(s)", regenerations);
+                            }
+
+                            {
+                                // Send the message(s) to the opponent, follow-ups after a short typing delay
+                                let mut messages = self.humanizer.humanize(&new_msg).into_iter();
+                                if let Some(first_msg) = messages.next() {
+                                    self.mpsc_sender.send(InterTaskMessageToNetworkTask::SendMsg {
+                                        msg: TcpMessage::Message(PlayerMessage {
+                                            msg: first_msg,
+                                            from_ai: true,
+                                            timestamp: SystemTime::now(),
+                                            to_ai: false,
+                                            regenerations,
+                                            filtered,
+                                        })
+                                    }).unwrap();
+                                }
+
+                                let follow_ups: Vec<String> = messages.collect();
+                                if !follow_ups.is_empty() {
+                                    let sender = self.mpsc_sender.clone();
+                                    let (lower_delay_limit, upper_delay_limit) = self.bot_typing_speed();
+                                    cancel_bot_follow_ups(self);
+                                    let follow_up_task = tokio::spawn(async move {
+                                        for msg in follow_ups {
+                                            tokio::time::sleep(bot::typing_delay(msg.chars().count(), lower_delay_limit, upper_delay_limit)).await;
+                                            let _ = sender.send(InterTaskMessageToNetworkTask::SendMsg {
+                                                msg: TcpMessage::Message(PlayerMessage {
+                                                    msg,
+                                                    from_ai: true,
+                                                    timestamp: SystemTime::now(),
+                                                    to_ai: false,
+                                                    regenerations,
+                                                    filtered,
+                                                })
+                                            });
+                                        }
+                                    });
+                                    self.bot_follow_ups = Some(follow_up_task.abort_handle());
+                                }
                             }
                         }
                     }
                 },
                 Err(e) => {
-                    eprintln!("Error receiving message: {}", e);
+                    error!("Error receiving message: {}", e);
                 }
             }
         }
 
+        if idle::user_input(ctx){
+            self.last_input_time = SystemTime::now();
+            if let Screen::Attract = self.screen{
+                self.screen = Screen::Start;
+            }
+        }
+
+        if let Screen::Start = self.screen{
+            let idle_secs = self.last_input_time.elapsed().map_or(0, |idle| idle.as_secs());
+            if !self.start_game_pressed && self.settings.attract_idle_secs > 0 && idle_secs >= self.settings.attract_idle_secs{
+                attract_screen::enter_attract_mode(self);
+            }
+        }
+
         if let Screen::Welcome = self.screen{
             if self.marked_as_ready && !self.marked_as_ready_opponent{
                 match self.waiting_for_ready_opponent_since{
@@ -401,34 +671,55 @@ impl eframe::App for ApplicationState{
             }
 
             if self.marked_as_ready && self.marked_as_ready_opponent{
+                self.round_difficulty = difficulty::agree_on_difficulty(&self.settings.difficulties, &self.difficulty, self.opponent_difficulty.as_deref()).cloned();
+                if let Some(difficulty) = &self.round_difficulty{
+                    info!("Playing with difficulty {}", difficulty.name);
+                }
+                // Pick the persona of our bot already now, so the prompt preview shows what the bot gets
+                self.persona = persona::pick_persona(&self.settings.personas, &self.bot_language(), self.settings.persona.as_deref()).cloned();
                 self.screen = Screen::Prompting;
                 self.prompting_start_time = Some(SystemTime::now())
             }
         }
         if let Screen::Prompting = self.screen{
-            if self.prompting_start_time.unwrap().elapsed().unwrap().as_secs() >= 90{
-                self.marked_as_prompt_ready = true;
-                self.mpsc_sender.send(InterTaskMessageToNetworkTask::SendMsg {
-                    msg: TcpMessage::PromptingFinished
-                }).unwrap();
+            if self.prompting_start_time.unwrap().elapsed().unwrap().as_secs() >= prompting_screen::PROMPTING_DURATION_SECS && !self.marked_as_prompt_ready && !self.prompt_moderation_pending{
+                prompting_screen::submit_custom_prompt(self, true);
             }
             if self.marked_as_prompt_ready && self.marked_as_prompt_ready_opponent{
-                // Create first messages for LLM
-                self.llm_history.push(LLMMessage{
-                    role: "developer".to_string(),
-                    content: self.settings.initial_prompt.clone(),
-                    refusal: None,
-                });
-                self.llm_history.push(LLMMessage{
-                    role: "developer".to_string(),
-                    content: self.custom_prompt.clone(),
-                    refusal: None,
-                });
+                // Tell the opponent about the persona of our bot, so the round can be recorded on both sides
+                let language = self.bot_language();
+                if let Some(persona) = &self.persona {
+                    info!("Bot plays persona {}", persona.name);
+                    self.mpsc_sender.send(InterTaskMessageToNetworkTask::SendMsg { msg: TcpMessage::PersonaSelected(persona.name.clone()) }).unwrap();
+                }
+                self.mpsc_sender.send(InterTaskMessageToNetworkTask::SendMsg { msg: TcpMessage::CustomPrompt {
+                    prompt: self.custom_prompt.trim().to_string(),
+                    category: self.custom_prompt_category.clone(),
+                } }).unwrap();
+                self.humanizer.set_typo_rate(self.persona.as_ref().and_then(|persona| persona.typo_rate).unwrap_or(self.settings.humanizer_typo_rate));
+                self.humanizer.set_intensity(self.round_difficulty.as_ref().map_or(1.0, |difficulty| difficulty.humanizer_intensity));
+
+                // Start a fresh conversation for the bot. The simulated opponent in solo mode never
+                // talks to it, so it stays idle instead of taking the initiative for nobody.
+                if self.settings.mode != Mode::Solo {
+                    let (lower_delay_limit, upper_delay_limit) = self.bot_typing_speed();
+                    let custom_prompt = moderation::layer_custom_prompt(&self.custom_prompt, &self.localization.custom_prompt_template(&language));
+                    self.bot_sender.send(InterTaskMessageToBot::StartRound {
+                        language,
+                        persona: self.persona.clone().map(Box::new),
+                        difficulty: self.round_difficulty.clone(),
+                        custom_prompt,
+                        round_id: self.round_id.clone(),
+                        lower_delay_limit,
+                        upper_delay_limit,
+                    }).unwrap();
+                }
 
                 self.screen = Screen::Game;
                 self.game_start_time = Some(SystemTime::now());
+                self.metrics.round_started();
             }
-            if self.prompting_start_time.unwrap().elapsed().unwrap().as_secs() >= 120{
+            if self.prompting_start_time.unwrap().elapsed().unwrap().as_secs() >= prompting_screen::PROMPTING_DURATION_SECS + 30{
                 // Prompting time is over since 30 seconds but the opponent hasn't marked as ready -> reset
                 // Save the human response times
                 save_human_response_times_to_file(&self.human_response_times_chars_per_second);
@@ -445,31 +736,35 @@ impl eframe::App for ApplicationState{
         }
 
         if let Screen::Game = self.screen{
-            if self.game_start_time.unwrap().elapsed().unwrap().as_secs() >= 210{
-                self.screen = Screen::End;
+            if self.game_start_time.unwrap().elapsed().unwrap().as_secs() >= self.game_duration_secs(){
+                if self.settings.record_transcripts && self.settings.mode != Mode::Solo{
+                    record_human_transcript(self);
+                }
+                end_chat(self);
             }
         }
 
-        if let Screen::End2 = self.screen{
-            if self.showing_end_screen_since.unwrap().elapsed().unwrap().as_secs() >= 10{
-                // Save the human response times
-                save_human_response_times_to_file(&self.human_response_times_chars_per_second);
-                // Calculate the new average chars per second
-                let (lower, upper) = calculate_average_chars_per_second_limits(&self.human_response_times_chars_per_second);
-                self.chars_per_second_lower = lower;
-                self.chars_per_second_upper = upper;
-
-                // Restart the app
-                reset_app_state(self);
-                self.mpsc_restart_sender.send(()).unwrap();
-                self.screen = Screen::Start;
+        if let Screen::Reveal = self.screen{
+            if self.settings.reveal_display_secs > 0 && self.showing_end_screen_since.unwrap().elapsed().unwrap().as_secs() >= self.settings.reveal_display_secs{
+                finish_round(self);
             }
         }
 
+        if self.settings.transport == Transport::Plain{
+            egui::TopBottomPanel::top("insecure_transport_banner").show(ctx, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.label(RichText::new(self.text("insecure_transport")).strong().color(egui::Color32::YELLOW));
+                });
+            });
+        }
+
         match self.screen{
             Screen::Start => {
                 start_screen::render_start_screen(self, ctx, frame);
             }
+            Screen::Attract => {
+                attract_screen::render_attract_screen(self, ctx, frame);
+            }
             Screen::Welcome => {
                 welcome_screen::render_welcome_screen(self, ctx, frame);
             },
@@ -482,10 +777,17 @@ impl eframe::App for ApplicationState{
             Screen::End => {
                 end_screen::render_end_screen(self, ctx, frame);
             },
-            Screen::End2 => {
-                end_screen::render_end_screen2(self, ctx, frame);
+            Screen::Reveal => {
+                reveal_screen::render_reveal_screen(self, ctx, frame);
+            }
+            Screen::Stats => {
+                stats::render_stats_screen(self, ctx, frame);
             }
         }
+
+        idle::check_idle(self, ctx);
+        spectator::publish_phase(self);
+        admin::render_admin_panel(self, ctx);
     }
 }
 
@@ -508,9 +810,25 @@ pub enum InterTaskMessageToGUI {
     ConnectionClosedUnexpectedly{
         error: String,
     },
-    HandleLLMResponse{
-        response: LLMResponseBundle,
-    }
+    /// Reply of the bot, to be sent to the opponent
+    BotReply{
+        msg: String,
+        /// How often the reply was regenerated by the output filter
+        regenerations: u8,
+        /// Whether the output filter changed or replaced the reply
+        filtered: bool,
+    },
+    /// Outcome of a request to the LLM, shown in the admin panel
+    LLMStatus{
+        latency: Option<Duration>,
+        error: Option<String>,
+    },
+    /// Result of checking the custom prompt with the moderation endpoint
+    ModerationResult{
+        flagged_categories: Vec<String>,
+        /// The prompt was submitted because the prompting time ran out
+        forced: bool,
+    },
 }
 
 #[derive(Debug, Clone, Default)]
@@ -523,14 +841,36 @@ pub enum InterTaskMessageToNetworkTask {
     SendMsg{
         msg: TcpMessage,
     },
-    ContactLLM{
-        msg: PlayerMessage,
-        history: Vec<LLMMessage>,
-        client: Client,
-        settings: Arc<Settings>,
+}
+
+#[derive(Debug, Clone)]
+pub enum InterTaskMessageToBot {
+    /// Start a fresh conversation with the initial prompt and the opponent's custom prompt and arm
+    /// the timer for taking the initiative
+    StartRound{
+        /// Language of the opponent, decides which prompts are used
+        language: String,
+        /// Replaces the initial prompt and provides the openers, if set
+        persona: Option<Box<Persona>>,
+        /// Picks the model and adds the strictness prompt, if set
+        difficulty: Option<Difficulty>,
+        custom_prompt: Option<String>,
+        /// The bot logs in a span with this id, like the GUI
+        round_id: String,
+        /// Typing speed limits in chars per second for the reply delay
         lower_delay_limit: f32,
         upper_delay_limit: f32,
     },
+    /// Message of the opponent to the AI
+    OpponentMessage{
+        msg: PlayerMessage,
+    },
+    /// The round is over, forget the conversation
+    EndRound,
+    /// Settings were changed in the admin panel
+    UpdateSettings{
+        settings: Arc<Settings>,
+    },
 }
 
 #[derive(Clone, Debug, Encode, Decode)]
@@ -539,11 +879,29 @@ pub struct PlayerMessage{
     pub from_ai: bool,
     pub to_ai: bool,
     pub timestamp: SystemTime,
+    /// How often the bot's reply was regenerated by the output filter
+    pub regenerations: u8,
+    /// Whether the output filter changed or replaced the bot's reply
+    pub filtered: bool,
 }
 
 #[derive(Clone, Debug, Encode, Decode)]
 pub enum TcpMessage{
     MarkedAsReady,
+    /// Language code picked by the player, sent before `MarkedAsReady`
+    LanguageSelected(String),
+    /// Name of the persona the sender's bot plays this round
+    PersonaSelected(String),
+    /// Name of the difficulty picked by the sender, sent whenever it changes and before `MarkedAsReady`
+    DifficultySelected(String),
+    /// The sender walked away, the round is aborted
+    Abandoned,
+    /// Custom prompt of the sender's player, shown on the opponent's reveal screen
+    CustomPrompt{
+        prompt: String,
+        /// Category of the last template inserted from the prompt library
+        category: Option<String>,
+    },
     PromptingFinished,
     Message(PlayerMessage),
     EndGame,
@@ -551,14 +909,131 @@ pub enum TcpMessage{
 
 #[tokio::main]
 pub async fn main()  {
-    let options = eframe::NativeOptions::default();
+    let cli = cli::Cli::parse();
+    let settings = match cli.settings() {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("Couldn't read config(s)!: {}", e);
+            std::process::exit(1);
+        }
+    };
 
+    match cli.command.clone().unwrap_or(cli::Command::Run) {
+        cli::Command::Run => {},
+        cli::Command::CheckConfig => {
+            println!("{:#?}", settings);
+            if settings.uses_mtls() {
+                if let Err(e) = certs::check_certs(&settings) {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+            println!("Config is valid");
+            return;
+        },
+        cli::Command::Certs => {
+            match certs::check_certs(&settings) {
+                Ok(summary) => println!("{}", summary),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        },
+        cli::Command::Stats { csv } => {
+            let _log_guard = logging::init(&settings);
+            stats::run_cli(&settings, csv.as_deref());
+            return;
+        },
+    }
+
+    let _log_guard = logging::init(&settings);
+    debug!("Loaded settings: {:?}", settings);
+
+    // Without usable certificates the booth could never connect, better not start at all
+    if settings.uses_mtls() {
+        if let Err(e) = certs::check_certs(&settings) {
+            error!("Couldn't set up mtls: {}", e);
+            eprintln!("Couldn't set up mtls: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    let mut options = eframe::NativeOptions::default();
+    if settings.fullscreen {
+        options.viewport = options.viewport.with_fullscreen(true);
+    }
+
+    match settings.mode {
+        Mode::Networked | Mode::Solo => {
+            let session = start_session(Arc::new(settings)).await;
+
+            eframe::run_native(
+                "The Turing Challenge",
+                options,
+                Box::new(|cc| Ok(Box::new({
+                    session.into_app(cc)
+                })),),
+            ).expect("Couldn't start GUI");
+        },
+        Mode::HotSeat => {
+            let (settings1, settings2) = settings.hot_seat_sessions();
+            let session1 = start_session(Arc::new(settings1)).await;
+            let session2 = start_session(Arc::new(settings2)).await;
+
+            eframe::run_native(
+                "The Turing Challenge - Player 1",
+                options,
+                Box::new(|cc| Ok(Box::new({
+                    hot_seat::HotSeatApp {
+                        player1: session1.into_app(cc),
+                        player2: session2.into_app(cc),
+                    }
+                })),),
+            ).expect("Couldn't start GUI");
+        }
+    }
+}
+
+/// Channels of a running network task, waiting for its GUI
+pub struct Session {
+    settings: Arc<Settings>,
+    sender_to_gui: broadcast::Sender<InterTaskMessageToGUI>,
+    sender_to_network: broadcast::Sender<InterTaskMessageToNetworkTask>,
+    receiver_from_network: broadcast::Receiver<InterTaskMessageToGUI>,
+    restart_sender: broadcast::Sender<()>,
+    bot_sender: broadcast::Sender<InterTaskMessageToBot>,
+    spectator_sender: broadcast::Sender<spectator::SpectatorEvent>,
+    metrics: Arc<metrics::Metrics>,
+    localization: Arc<Localization>,
+}
+
+impl Session {
+    fn into_app(self, cc: &eframe::CreationContext<'_>) -> ApplicationState {
+        ApplicationState::new(cc, self)
+    }
+}
+
+/// Start a network task for one player and wait until it is ready
+async fn start_session(settings: Arc<Settings>) -> Session {
     let (sender_to_gui, mut receiver_from_network) = broadcast::channel::<InterTaskMessageToGUI>(100);
 
-    let (restart_sender, restart_receiver) = tokio::sync::broadcast::channel::<()>(1);
+    let (restart_sender, _) = tokio::sync::broadcast::channel::<()>(1);
 
     // Start the network task
-    network::spawn_network_task(sender_to_gui.clone(), restart_sender.subscribe(), restart_sender.subscribe(), restart_sender.subscribe());
+    network::spawn_network_task(settings.clone(), sender_to_gui.clone(), restart_sender.subscribe(), restart_sender.subscribe(), restart_sender.subscribe());
+
+    let localization = Arc::new(Localization::load(&settings));
+
+    // Start the bot task
+    let bot_sender = bot::spawn_bot_task(settings.clone(), localization.clone(), sender_to_gui.clone());
+
+    // Start the spectator feed, if enabled
+    let spectator_sender = spectator::spawn_spectator_server(settings.clone());
+
+    // Serve the health metrics, if enabled
+    let metrics = metrics::spawn_metrics_server(settings.clone());
 
     // Get the sender to the network task
     let msg = receiver_from_network.recv().await.unwrap();
@@ -572,13 +1047,17 @@ pub async fn main()  {
         panic!("Expected MspcSender message from network task");
     };
 
-    eframe::run_native(
-        "The Turing Challenge",
-        options,
-        Box::new(|cc| Ok(Box::new({
-            ApplicationState::new(cc, sender_to_network, receiver_from_network, restart_sender.clone())
-        })),),
-    ).expect("Couldn't start GUI");
+    Session {
+        settings,
+        sender_to_gui,
+        sender_to_network,
+        receiver_from_network,
+        restart_sender,
+        bot_sender,
+        spectator_sender,
+        metrics,
+        localization,
+    }
 }
 
 /// Calculate the average chars per second from a list of response times
@@ -603,14 +1082,114 @@ fn calculate_average_chars_per_second_limits(response_times: &Vec<f32>) -> (f32,
     let avg = avg * 1000.0;
     let std_dev = std_dev * 1000.0;
 
-    println!("Average: {}, Std Dev: {}", avg, std_dev);
-    (avg - std_dev, avg + std_dev)
+    debug!("Average: {}, Std Dev: {}", avg, std_dev);
+    if !avg.is_finite() || !std_dev.is_finite() || avg <= bot::MIN_CHARS_PER_SECOND {
+        return (2.0, 3.5);
+    }
+    // A single response time (or identical ones) has no deviation, keep some variation anyway
+    let spread = std_dev.max(avg * 0.1);
+    let lower = (avg - spread).max(bot::MIN_CHARS_PER_SECOND);
+    let upper = (avg + spread).min(bot::MAX_CHARS_PER_SECOND);
+    if lower < upper {
+        (lower, upper)
+    } else {
+        (2.0, 3.5)
+    }
+}
+
+/// Save the messages the human opponent wrote this round, so they can be replayed in solo mode
+fn record_human_transcript(state: &ApplicationState){
+    let human_chat_history = if state.human_chat == 0 { &state.chat1_history } else { &state.chat2_history };
+    let messages: Vec<String> = human_chat_history.iter()
+        .filter(|msg| matches!(msg.from, ChatMessageOrigin::Foreign))
+        .map(|msg| msg.message.clone())
+        .collect();
+
+    transcripts::save_transcript(&state.settings.transcripts_dir, &messages);
+}
+
+/// Append the round which just ended to the round records
+fn record_round(state: &ApplicationState){
+    let Some(started_at) = state.game_start_time else {
+        // The chat never started
+        return;
+    };
+
+    let record = rounds::RoundRecord {
+        player_name: Some(state.name.trim().to_string()).filter(|name| !name.is_empty()),
+        language: state.language.clone(),
+        persona: state.persona.as_ref().map(|persona| persona.name.clone()),
+        opponent_persona: state.opponent_persona.clone(),
+        difficulty: state.round_difficulty.as_ref().map(|difficulty| difficulty.name.clone()),
+        model: Some(state.round_difficulty.as_ref().and_then(|difficulty| difficulty.model).unwrap_or(state.settings.llm_model).to_string()),
+        opponent_custom_prompt: state.opponent_custom_prompt.clone().filter(|prompt| !prompt.is_empty()),
+        opponent_prompt_category: state.opponent_prompt_category.clone(),
+        human_chat_messages: if state.human_chat == 0 { state.chat1_history.len() } else { state.chat2_history.len() },
+        ai_chat_messages: if state.human_chat == 0 { state.chat2_history.len() } else { state.chat1_history.len() },
+        correctly_guessed: state.correctly_guessed,
+        confidence: state.correctly_guessed.map(|_| state.guess_confidence),
+        reasoning: Some(state.guess_reasoning.trim().to_string()).filter(|reasoning| state.correctly_guessed.is_some() && !reasoning.is_empty()),
+        id: state.round_id.clone(),
+        ..rounds::RoundRecord::new(started_at)
+    };
+    rounds::save_round(&state.settings.rounds_file, &record);
+}
+
+/// Duration as minutes for the UI, e.g. "3,5" for 210 seconds with a comma as decimal separator
+fn format_minutes(secs: u64, decimal_separator: &str) -> String {
+    if secs.is_multiple_of(60) {
+        (secs / 60).to_string()
+    } else {
+        format!("{:.1}", secs as f64 / 60.0).replace('.', decimal_separator)
+    }
+}
+
+/// Whether the chat is over and the player is guessing or looking at the reveal. The opponent
+/// isn't needed anymore then, so the round can be finished even if they leave.
+fn chat_is_over(screen: &Screen) -> bool {
+    matches!(screen, Screen::End | Screen::Reveal)
+}
+
+/// End the chat phase and stop the bot, so no late reply or follow-up reaches the opponent
+fn end_chat(state: &mut ApplicationState){
+    state.screen = Screen::End;
+    let _ = state.bot_sender.send(InterTaskMessageToBot::EndRound);
+    cancel_bot_follow_ups(state);
+}
+
+fn cancel_bot_follow_ups(state: &mut ApplicationState){
+    if let Some(follow_ups) = state.bot_follow_ups.take() {
+        follow_ups.abort();
+    }
+}
+
+/// Leave the reveal screen and get ready for the next round
+fn finish_round(state: &mut ApplicationState){
+    // Save the human response times
+    save_human_response_times_to_file(&state.human_response_times_chars_per_second);
+    // Calculate the new average chars per second
+    let (lower, upper) = calculate_average_chars_per_second_limits(&state.human_response_times_chars_per_second);
+    state.chars_per_second_lower = lower;
+    state.chars_per_second_upper = upper;
+
+    // Restart the app
+    reset_app_state(state);
+    state.mpsc_restart_sender.send(()).unwrap();
+    state.screen = Screen::Start;
+}
+
+/// Abort the current round and go back to the start screen
+fn force_reset(state: &mut ApplicationState){
+    save_human_response_times_to_file(&state.human_response_times_chars_per_second);
+    reset_app_state(state);
+    state.mpsc_restart_sender.send(()).unwrap();
 }
 
 fn reset_app_state(state: &mut ApplicationState){
+    record_round(state);
+
     let mut rng = rand::thread_rng();
     let human_chat : u8= rng.gen_range(0..=1);
-    let llm_take_iniative_after = rng.gen_range(state.settings.llm_take_initiative_after_lower_bound..=state.settings.llm_take_initiative_after_upper_bound);
     let (lower_limit, upper_limit) = calculate_average_chars_per_second_limits(&state.human_response_times_chars_per_second);
 
     state.start_game_pressed = false;
@@ -618,6 +1197,23 @@ fn reset_app_state(state: &mut ApplicationState){
     state.name = "".to_string();
     state.warning = None;
     state.custom_prompt = "".to_string();
+    state.custom_prompt_category = None;
+    state.prompt_moderation_pending = false;
+    state.prompt_moderation_warning = None;
+    state.language = state.localization.default_language().to_string();
+    state.opponent_language = None;
+    state.persona = None;
+    state.opponent_persona = None;
+    state.opponent_custom_prompt = None;
+    state.round_span = tracing::Span::none();
+    state.last_message_to_bot_time = None;
+    cancel_bot_follow_ups(state);
+    state.opponent_prompt_category = None;
+    state.difficulty = state.settings.default_difficulty.clone();
+    state.opponent_difficulty = None;
+    state.round_difficulty = None;
+    // The connection is closed on every reset
+    state.admin.peer = None;
     state.marked_as_ready = false;
     state.marked_as_ready_opponent = false;
     state.marked_as_prompt_ready = false;
@@ -629,13 +1225,13 @@ fn reset_app_state(state: &mut ApplicationState){
     state.human_chat = human_chat;
     state.prompting_start_time = None;
     state.game_start_time = None;
-    state.llm_history = vec![];
-    state.llm_take_iniative_after = llm_take_iniative_after as u8;
-    state.llm_chat_first_message = false;
+    let _ = state.bot_sender.send(InterTaskMessageToBot::EndRound);
     state.correctly_guessed = None;
+    state.guessed_chat = None;
+    state.guess_confidence = 50;
+    state.guess_reasoning = "".to_string();
     state.showing_end_screen_since = None;
     state.waiting_for_ready_opponent_since = None;
-    state.llm_last_message_time = None;
     state.chars_per_second_upper = upper_limit;
     state.chars_per_second_lower = lower_limit;
 }
@@ -644,9 +1240,9 @@ fn load_previous_human_response_times() -> Vec<f32>{
     let file = match File::open("response_times.txt") {
         Ok(f) => f,
         Err(e) => {
-            println!("Failed to open file: {}. Trying to create it.", e);
+            warn!("Failed to open file: {}. Trying to create it.", e);
             if let Err(e) = File::create("response_times.txt") {
-                println!("Failed to create file: {}", e);
+                warn!("Failed to create file: {}", e);
             }
             return vec![];
         }
@@ -663,12 +1259,12 @@ fn load_previous_human_response_times() -> Vec<f32>{
                         values.push(v);
                     },
                     Err(e) => {
-                        println!("Failed to parse line: {}", e);
+                        warn!("Failed to parse line: {}", e);
                     }
                 }
             },
             Err(e) => {
-                println!("Failed to read line: {}", e);
+                warn!("Failed to read line: {}", e);
             }
         }
     }
@@ -677,11 +1273,11 @@ fn load_previous_human_response_times() -> Vec<f32>{
 }
 
 fn save_human_response_times_to_file(values: &Vec<f32>){
-    println!("Saving response times to file");
+    debug!("Saving response times to file");
     let file = match File::create("response_times.txt") {
         Ok(f) => f,
         Err(e) => {
-            println!("Failed to create file: {}", e);
+            warn!("Failed to create file: {}", e);
             return;
         }
     };
@@ -692,8 +1288,35 @@ fn save_human_response_times_to_file(values: &Vec<f32>){
         match writeln!(writer, "{}", v){
             Ok(_) => {},
             Err(e) => {
-                println!("Failed to write line: {}", e);
+                warn!("Failed to write line: {}", e);
             }
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minutes_use_the_decimal_separator() {
+        assert_eq!(format_minutes(210, ","), "3,5");
+        assert_eq!(format_minutes(90, "."), "1.5");
+        assert_eq!(format_minutes(180, ","), "3");
+    }
+
+    #[test]
+    fn typing_speed_limits_are_finite_and_ordered() {
+        for response_times in [vec![], vec![0.003], vec![0.003, 0.003], vec![0.0], vec![f32::NAN], vec![f32::INFINITY], vec![0.001, 0.010]] {
+            let (lower, upper) = calculate_average_chars_per_second_limits(&response_times);
+            assert!(lower.is_finite() && upper.is_finite(), "{:?}", response_times);
+            assert!(lower < upper, "{:?}", response_times);
+            assert!(lower >= bot::MIN_CHARS_PER_SECOND, "{:?}", response_times);
+        }
+    }
+
+    #[test]
+    fn single_response_time_keeps_its_speed() {
+        let (lower, upper) = calculate_average_chars_per_second_limits(&vec![0.004]);
+        assert!(lower < 4.0 && 4.0 < upper);
+    }
+}
@@ -0,0 +1,146 @@
+use std::sync::Arc;
+use std::time::Duration;
+use axum::extract::State;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use prometheus::{Encoder, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder};
+use tracing::{error, info};
+use crate::settings::Settings;
+
+/// Phases reported by the `turing_phase` gauge, the same names the spectator feed uses
+pub const PHASES: [&str; 6] = ["idle", "welcome", "prompting", "chat", "guessing", "reveal"];
+
+/// Health metrics of one booth, served in the Prometheus text format on `/metrics`
+#[derive(Debug)]
+pub struct Metrics {
+    registry: Registry,
+    rounds: IntCounter,
+    guesses: IntCounterVec,
+    connections: IntCounter,
+    connection_failures: IntCounter,
+    connection_drops: IntCounter,
+    llm_errors: IntCounter,
+    llm_latency: Histogram,
+    message_delay: Histogram,
+    phase: IntGaugeVec,
+}
+
+impl Metrics {
+    pub fn new(booth: &str) -> Self {
+        let registry = Registry::new_custom(Some("turing".to_string()), Some([("booth".to_string(), booth.to_string())].into())).unwrap();
+
+        let metrics = Metrics {
+            rounds: IntCounter::new("rounds_total", "Rounds in which the chat started").unwrap(),
+            guesses: IntCounterVec::new(Opts::new("guesses_total", "Guesses of the player by result"), &["result"]).unwrap(),
+            connections: IntCounter::new("connections_total", "Connections to the other booth, a new one is made every round").unwrap(),
+            connection_failures: IntCounter::new("connection_failures_total", "Failed attempts to connect to the other booth").unwrap(),
+            connection_drops: IntCounter::new("connection_drops_total", "Connections to the other booth closed unexpectedly").unwrap(),
+            llm_errors: IntCounter::new("llm_errors_total", "Failed requests to the LLM").unwrap(),
+            llm_latency: Histogram::with_opts(HistogramOpts::new("llm_latency_seconds", "Duration of successful LLM requests")
+                .buckets(vec![0.25, 0.5, 1.0, 2.0, 3.0, 5.0, 8.0, 13.0, 20.0, 30.0])).unwrap(),
+            message_delay: Histogram::with_opts(HistogramOpts::new("message_delay_seconds", "Time between a message of the opponent and the bot's reply, including the typing delay")
+                .buckets(vec![1.0, 2.0, 4.0, 6.0, 8.0, 10.0, 15.0, 20.0, 30.0, 45.0, 60.0])).unwrap(),
+            phase: IntGaugeVec::new(Opts::new("phase", "1 for the phase the booth is in, 0 for all others"), &["phase"]).unwrap(),
+            registry,
+        };
+
+        metrics.registry.register(Box::new(metrics.rounds.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.guesses.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.connections.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.connection_failures.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.connection_drops.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.llm_errors.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.llm_latency.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.message_delay.clone())).unwrap();
+        metrics.registry.register(Box::new(metrics.phase.clone())).unwrap();
+
+        // Report all guess results and phases from the start, so rates work right away
+        metrics.guesses.with_label_values(&["correct"]);
+        metrics.guesses.with_label_values(&["wrong"]);
+        metrics.set_phase("idle");
+
+        metrics
+    }
+
+    pub fn round_started(&self) {
+        self.rounds.inc();
+    }
+
+    pub fn guessed(&self, correct: bool) {
+        self.guesses.with_label_values(&[if correct { "correct" } else { "wrong" }]).inc();
+    }
+
+    pub fn connected(&self) {
+        self.connections.inc();
+    }
+
+    pub fn connection_failed(&self) {
+        self.connection_failures.inc();
+    }
+
+    pub fn connection_dropped(&self) {
+        self.connection_drops.inc();
+    }
+
+    /// Outcome of an LLM request, as reported by the bot task
+    pub fn llm_request(&self, latency: Option<Duration>, failed: bool) {
+        if let Some(latency) = latency {
+            self.llm_latency.observe(latency.as_secs_f64());
+        }
+        if failed {
+            self.llm_errors.inc();
+        }
+    }
+
+    pub fn message_delay(&self, delay: Duration) {
+        self.message_delay.observe(delay.as_secs_f64());
+    }
+
+    pub fn set_phase(&self, phase: &str) {
+        for name in PHASES {
+            self.phase.with_label_values(&[name]).set(if name == phase { 1 } else { 0 });
+        }
+    }
+
+    fn encode(&self) -> String {
+        let mut buffer = vec![];
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            error!("Failed to encode metrics: {}", e);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+/// Create the metrics of a booth and serve them on `metrics_bind_host`:`metrics_port`, unless the port is 0
+pub fn spawn_metrics_server(settings: Arc<Settings>) -> Arc<Metrics> {
+    let metrics = Arc::new(Metrics::new(&settings.booth_name));
+    if settings.metrics_port == 0 {
+        return metrics;
+    }
+
+    let address = format!("{}:{}", settings.metrics_bind_host, settings.metrics_port);
+    let app = Router::new()
+        .route("/metrics", get(serve_metrics))
+        .with_state(metrics.clone());
+    tokio::spawn(async move {
+        let listener = match tokio::net::TcpListener::bind(&address).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Couldn't start metrics server on {}: {}", address, e);
+                return;
+            }
+        };
+        info!("Metrics available on http://{}/metrics", address);
+        if let Err(e) = axum::serve(listener, app).await {
+            error!("Metrics server stopped: {}", e);
+        }
+    });
+
+    metrics
+}
+
+async fn serve_metrics(State(metrics): State<Arc<Metrics>>) -> impl IntoResponse {
+    ([(CONTENT_TYPE, TextEncoder::new().format_type().to_string())], metrics.encode())
+}
@@ -1,7 +1,6 @@
 use std::sync::Arc;
 use std::time::Duration;
-use rand::Rng;
-use tokio::io::{AsyncReadExt, AsyncWriteExt, ReadHalf, WriteHalf};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadHalf, WriteHalf};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::broadcast;
 use tokio::sync::broadcast::Sender;
@@ -11,58 +10,89 @@ use tokio_rustls::rustls::{ClientConfig, ServerConfig};
 use tokio_rustls::{TlsConnector, TlsStream};
 use tokio_rustls::rustls::pki_types::ServerName;
 use tokio_rustls::TlsAcceptor;
+use tracing::{debug, error, info, info_span, warn, Instrument, Span};
 use crate::certs::{load_client_cert, load_private_key, load_root_ca};
-use crate::{settings, InterTaskMessageToGUI, InterTaskMessageToNetworkTask, LLMMessage, LLMModel, LLMRequest, LLMResponse, LLMResponseBundle, PlayerMessage, TcpMessage};
+use crate::settings::{Mode, Transport};
+use crate::{settings, solo, InterTaskMessageToGUI, InterTaskMessageToNetworkTask, TcpMessage};
+use crate::logging::CHAT;
 
-pub fn spawn_network_task(mpsc_sender: tokio::sync::broadcast::Sender<InterTaskMessageToGUI>, mut restart_receiver: tokio::sync::broadcast::Receiver<()>, restart_receiver2: tokio::sync::broadcast::Receiver<()>, restart_receiver3: tokio::sync::broadcast::Receiver<()>) {
-    tokio::spawn(async move {
-        let settings: Arc<settings::Settings> = Arc::new(settings::Settings::new().expect("Couldn't read config(s)!"));
+/// Byte stream to the opponent booth, either TLS or plain TCP
+pub trait PeerStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> PeerStream for T {}
 
-        // Load mtls certs
-        let root_ca = Arc::new(load_root_ca(settings.root_ca.clone()));
-        let client_cert = load_client_cert(settings.client_cert.clone());
-        let client_key = load_private_key(settings.client_key.clone());
+pub type BoxedPeerStream = Box<dyn PeerStream>;
 
-        // Server Config
-        let client_verifier = WebPkiClientVerifier::builder(root_ca.clone()).build().expect("Couldn't build Client Verifier. Check Certs & Key!");
+/// Build the mtls server & client configs from the configured certs
+pub fn build_tls_configs(settings: &settings::Settings) -> Result<(Arc<ServerConfig>, Arc<ClientConfig>), String> {
+    // Load mtls certs
+    let root_ca = Arc::new(load_root_ca(settings.root_ca.clone())?);
+    let client_cert = load_client_cert(settings.client_cert.clone())?;
+    let client_key = load_private_key(settings.client_key.clone())?;
 
-        let server_config = Arc::new(ServerConfig::builder_with_protocol_versions(&[&tokio_rustls::rustls::version::TLS13])
-            .with_client_cert_verifier(client_verifier)
-            .with_single_cert(client_cert.clone(), client_key.clone_key()).expect("Couldn't build Server Config. Check Certs & Key!"));
+    // Server Config
+    let client_verifier = WebPkiClientVerifier::builder(root_ca.clone()).build().map_err(|e| format!("Couldn't build Client Verifier. Check Certs & Key! {}", e))?;
 
-        // Client Config
-        let client_config = Arc::new(ClientConfig::builder_with_protocol_versions(&[&tokio_rustls::rustls::version::TLS13])
-            .with_root_certificates(root_ca)
-            .with_client_auth_cert(client_cert, client_key).expect("Couldn't build Client Config. Check Certs & Key!"));
+    let server_config = Arc::new(ServerConfig::builder_with_protocol_versions(&[&tokio_rustls::rustls::version::TLS13])
+        .with_client_cert_verifier(client_verifier)
+        .with_single_cert(client_cert.clone(), client_key.clone_key()).map_err(|e| format!("Couldn't build Server Config. Check Certs & Key! {}", e))?);
 
+    // Client Config
+    let client_config = Arc::new(ClientConfig::builder_with_protocol_versions(&[&tokio_rustls::rustls::version::TLS13])
+        .with_root_certificates(root_ca)
+        .with_client_auth_cert(client_cert, client_key).map_err(|e| format!("Couldn't build Client Config. Check Certs & Key! {}", e))?);
+
+    Ok((server_config, client_config))
+}
 
+pub fn spawn_network_task(settings: Arc<settings::Settings>, mpsc_sender: tokio::sync::broadcast::Sender<InterTaskMessageToGUI>, mut restart_receiver: tokio::sync::broadcast::Receiver<()>, restart_receiver2: tokio::sync::broadcast::Receiver<()>, restart_receiver3: tokio::sync::broadcast::Receiver<()>) {
+    tokio::spawn(async move {
         // Create Server to listen on incoming rendering requests
         let sender_to_gui = Arc::new(mpsc_sender.clone());
 
         // Create second mpsc channel to receive messages from the GUI
         let (gui_sender, gui_receiver) = tokio::sync::broadcast::channel::<InterTaskMessageToNetworkTask>(30);
 
-        // Send the GUI sender to the GUI task
+        // Send the GUI sender to the GUI task first, it waits for it before opening the window
         sender_to_gui.send(InterTaskMessageToGUI::MspcSender { sender: gui_sender.clone() }).unwrap();
 
-        println!("Started network worker task. Listening for incoming connections...");
+        // main() already checked the certificates, this only fails if they changed since
+        let tls_configs = if settings.uses_mtls() {
+            match build_tls_configs(&settings) {
+                Ok(tls_configs) => Some(tls_configs),
+                Err(e) => {
+                    error!("Couldn't set up mtls, not connecting to the other booth: {}", e);
+                    return;
+                }
+            }
+        } else {
+            if settings.transport == Transport::Plain && settings.mode != Mode::Solo {
+                warn!("Plaintext transport is enabled. The connection to the other booth is neither encrypted nor authenticated!");
+            }
+            None
+        };
+
+        info!("Started network worker task. Listening for incoming connections...");
 
-        async fn main_worker_task(client_config: Arc<ClientConfig>, listener: TcpListener, acceptor: TlsAcceptor, sender_to_gui: Arc<Sender<InterTaskMessageToGUI>>, mut gui_receiver: broadcast::Receiver<InterTaskMessageToNetworkTask>, restart_receiver2: tokio::sync::broadcast::Receiver<()>, restart_receiver3: tokio::sync::broadcast::Receiver<()>) -> Option<TlsStream<TcpStream>> {
+        async fn main_worker_task(settings: Arc<settings::Settings>, tls: Option<(TlsAcceptor, Arc<ClientConfig>)>, listener: TcpListener, sender_to_gui: Arc<Sender<InterTaskMessageToGUI>>, mut gui_receiver: broadcast::Receiver<InterTaskMessageToNetworkTask>, restart_receiver2: tokio::sync::broadcast::Receiver<()>, restart_receiver3: tokio::sync::broadcast::Receiver<()>) -> Option<BoxedPeerStream> {
             let res = loop {
                 let waiter = listener.accept();
                 tokio::select! {
                     Ok((stream, connected_with)) = waiter => {
-                        println!("Received connection from {}", connected_with);
-                        let acceptor = acceptor.clone();
+                        info!("Received connection from {}", connected_with);
+                        let Some((acceptor, _)) = &tls else {
+                            sender_to_gui.send(InterTaskMessageToGUI::Connected{ with: connected_with.to_string() }).unwrap();
+                            break Some(Box::new(stream) as BoxedPeerStream);
+                        };
                         match acceptor.accept(stream).await{
                             Ok(tls_stream1) => {
-                                println!("TLS Handshake successful");
+                                debug!("TLS Handshake successful");
                                 let tls_stream = TlsStream::from(tls_stream1);
                                 sender_to_gui.send(InterTaskMessageToGUI::Connected{ with: connected_with.to_string() }).unwrap();
-                                break Some(tls_stream);
+                                break Some(Box::new(tls_stream) as BoxedPeerStream);
                             },
                             Err(e) => {
-                                eprintln!("TLS Handshake failed: {}", e);
+                                error!("TLS Handshake failed: {}", e);
                             }
                         }
                     }
@@ -75,305 +105,255 @@ pub fn spawn_network_task(mpsc_sender: tokio::sync::broadcast::Sender<InterTaskM
                                         },
 
                                     InterTaskMessageToNetworkTask::ConnectTo{ host_string } => {
+                                        if settings.mode == Mode::Solo {
+                                            // There is no opponent booth, talk to a simulated one instead
+                                            let (stream, opponent_stream) = tokio::io::duplex(64 * 1024);
+                                            solo::spawn_virtual_opponent(opponent_stream, settings.clone());
+                                            sender_to_gui.send(InterTaskMessageToGUI::Connected{ with: "virtual opponent".to_string() }).unwrap();
+                                            break Some(Box::new(stream) as BoxedPeerStream);
+                                        }
+
                                         // Create client connection
-                                        let connector = TlsConnector::from(client_config.clone());
                                         match timeout(Duration::from_secs(5), TcpStream::connect(host_string.clone())).await{
                                             Ok(Ok(stream)) => {
+                                                let Some((_, client_config)) = &tls else {
+                                                    info!("Connected (plaintext)!");
+                                                    sender_to_gui.send(InterTaskMessageToGUI::Connected{ with: host_string.clone() }).unwrap();
+                                                    break Some(Box::new(stream) as BoxedPeerStream);
+                                                };
+                                                let connector = TlsConnector::from(client_config.clone());
                                                 match timeout(Duration::from_secs(5), connector.connect(ServerName::try_from("localhost").unwrap(), stream)).await{
                                                     Ok(Ok(tls_stream1)) => {
-                                                        println!("Connected!");
-                                                        let tls_stream = Some(TlsStream::from(tls_stream1));
+                                                        info!("Connected!");
+                                                        let tls_stream = TlsStream::from(tls_stream1);
                                                         sender_to_gui.send(InterTaskMessageToGUI::Connected{ with: host_string.clone() }).unwrap();
-                                                        break tls_stream;
+                                                        break Some(Box::new(tls_stream) as BoxedPeerStream);
                                                     },
                                                     Ok(Err(e)) => {
                                                         sender_to_gui.send(InterTaskMessageToGUI::ConnectionFailed{error: format!("Couldn't connect to {}: {}", host_string, e)}).unwrap();
-                                                        eprintln!("Couldn't connect to {}: {}", host_string, e);
+                                                        error!("Couldn't connect to {}: {}", host_string, e);
                                                     },
                                                     Err(_) => {
                                                         sender_to_gui.send(InterTaskMessageToGUI::ConnectionFailed{error: format!("Couldn't connect to {}: Timeout", host_string)}).unwrap();
-                                                        eprintln!("Couldn't connect to {}: Timeout!", host_string);
+                                                        error!("Couldn't connect to {}: Timeout!", host_string);
                                                     }
                                                 }
                                             },
                                             Ok(Err(e)) => {
                                                 sender_to_gui.send(InterTaskMessageToGUI::ConnectionFailed{error: format!("Couldn't connect to {}: {}", host_string, e)}).unwrap();
-                                                println!("Couldn't connect to {}: {}", host_string, e);
+                                                warn!("Couldn't connect to {}: {}", host_string, e);
                                             },
                                             Err(_) => {
                                                 sender_to_gui.send(InterTaskMessageToGUI::ConnectionFailed{error: format!("Couldn't connect to {}: Timeout", host_string)}).unwrap();
-                                                println!("Couldn't connect to {}: Timeout!", host_string);
+                                                warn!("Couldn't connect to {}: Timeout!", host_string);
                                             }
                                         }
                                     },
                                     _ => {
-                                        eprintln!("Unexpected message from GUI: {:?}", msg);
+                                        error!("Unexpected message from GUI: {:?}", msg);
                                     }}
                                 }
                                 Err(e) => {
-                                    eprintln!("Couldn't receive message from GUI: {}", e);
+                                    error!("Couldn't receive message from GUI: {}", e);
                             }
                             }
                         }
                     }
             };
-            println!("loop returned: {:?}", res);
+            debug!("loop returned, connected: {}", res.is_some());
             res
         }
 
+        let mut connection_number: u64 = 0;
         loop {
-            println!("Starting network task");
-            let acceptor = TlsAcceptor::from(server_config.clone());
+            debug!("Starting network task");
+            // Everything logged until the next restart belongs to this connection
+            connection_number += 1;
+            let connection_span = info_span!("connection", number = connection_number);
+            let tls = tls_configs.as_ref().map(|(server_config, client_config)| (TlsAcceptor::from(server_config.clone()), client_config.clone()));
             let listener = TcpListener::bind(format!("{}:{}", settings.bind_to_host, settings.port)).await.unwrap();
 
-            let res = main_worker_task(client_config.clone(), listener, acceptor, sender_to_gui.clone(), gui_sender.subscribe(), restart_receiver2.resubscribe(), restart_receiver3.resubscribe()).await;
+            let res = main_worker_task(settings.clone(), tls, listener, sender_to_gui.clone(), gui_sender.subscribe(), restart_receiver2.resubscribe(), restart_receiver3.resubscribe())
+                .instrument(connection_span.clone()).await;
 
-            if let Some(tls_stream) = res {
-                println!("Handling incoming connection");
+            if let Some(stream) = res {
+                debug!("Handling incoming connection");
 
                 // Create two tasks to handle incoming and outgoing messages
-                let (reader, writer) = tokio::io::split(tls_stream);
-                handle_writer(writer, gui_receiver.resubscribe(), sender_to_gui.clone(), restart_receiver2.resubscribe());
-                handle_reader(reader, sender_to_gui.clone(), restart_receiver3.resubscribe());
+                let (reader, writer) = tokio::io::split(stream);
+                handle_writer(writer, gui_receiver.resubscribe(), sender_to_gui.clone(), restart_receiver2.resubscribe(), connection_span.clone());
+                handle_reader(reader, sender_to_gui.clone(), restart_receiver3.resubscribe(), connection_span);
             }
 
             restart_receiver.recv().await.expect("Restart receiver was closed :(");
-            println!("Restarting network task");
+            debug!("Restarting network task");
         }
     });
 }
 
-pub fn handle_writer(writer: WriteHalf<TlsStream<TcpStream>>, receiver_from_gui: broadcast::Receiver<InterTaskMessageToNetworkTask>, sender_to_gui: Arc<Sender<InterTaskMessageToGUI>>, mut restart_receiver: tokio::sync::broadcast::Receiver<()>) {
+pub fn handle_writer(writer: WriteHalf<BoxedPeerStream>, receiver_from_gui: broadcast::Receiver<InterTaskMessageToNetworkTask>, sender_to_gui: Arc<Sender<InterTaskMessageToGUI>>, mut restart_receiver: tokio::sync::broadcast::Receiver<()>, span: Span) {
     tokio::spawn(async move {
-        async fn loop_write(mut writer: WriteHalf<TlsStream<TcpStream>>, mut receiver_from_gui: broadcast::Receiver<InterTaskMessageToNetworkTask>, sender_to_gui: Arc<Sender<InterTaskMessageToGUI>>) -> Result<(), String> {
+        async fn loop_write(mut writer: WriteHalf<BoxedPeerStream>, mut receiver_from_gui: broadcast::Receiver<InterTaskMessageToNetworkTask>) -> Result<(), String> {
             let res = loop {
                 let msg_from_gui = receiver_from_gui.recv().await;
                 match msg_from_gui {
                     Ok(msg_from_gui) => {
                         match msg_from_gui {
                             InterTaskMessageToNetworkTask::SendMsg { msg } => {
-                                println!("Sending message: {:?}", msg);
-                                let encoded_msg = match bincode::encode_to_vec(msg, bincode::config::standard()) {
-                                    Ok(msg) => msg,
-                                    Err(e) => {
-                                        eprintln!("Couldn't encode message: {}", e);
-                                        break Err(e.to_string());
-                                    }
-                                };
-                                let len = encoded_msg.len() as u64;
-
-                                println!("Sending message of length {}", len);
-
-                                // Send length via socket
-                                match timeout(Duration::from_secs(5), writer.write_u64(len)).await {
-                                    Ok(Err(e)) => {
-                                        eprintln!("Couldn't send message length: {}", e);
-                                        break Err(format!("Couldn't send message length: {}", e));
-                                    },
-                                    Err(_) => {
-                                        eprintln!("Couldn't send message length: Timeout");
-                                        break Err("Couldn't send message length: Timeout".to_string());
-                                    },
-                                    _ => {}
-                                }
-
-                                match timeout(Duration::from_secs(5), writer.write_all(&encoded_msg[..])).await {
-                                    Ok(Err(e)) => {
-                                        eprintln!("Couldn't send message: {}", e);
-                                        break Err(format!("Couldn't send message: {}", e));
-                                    },
-                                    Err(_) => {
-                                        eprintln!("Couldn't send message: Timeout");
-                                        break Err("Couldn't send message: Timeout".to_string());
-                                    },
-                                    _ => {}
-                                }
-
-                                match timeout(Duration::from_secs(5), writer.flush()).await {
-                                    Ok(Err(e)) => {
-                                        eprintln!("Couldn't flush message: {}", e);
-                                        break Err(format!("Couldn't flush message: {}", e));
-                                    },
-                                    Err(_) => {
-                                        eprintln!("Couldn't flush message: Timeout");
-                                        break Err("Couldn't flush message: Timeout".to_string());
-                                    },
-                                    _ => {}
-                                }
-                            }
-                            InterTaskMessageToNetworkTask::ContactLLM { msg, history, client, settings, lower_delay_limit, upper_delay_limit } => {
-                                let resp = tokio::time::timeout(Duration::from_secs(30), talk_to_llm(msg, history, client, settings)).await;
-
-                                match resp {
-                                    Ok(resp) => {
-                                        let sender = sender_to_gui.clone();
-                                        tokio::spawn(async move {
-                                            if let Some(msg) = &resp.new_message_from_llm {
-                                                // calculate random delay before sending responsedelay
-
-                                                let num_of_chars = msg.chars().count();
-
-                                                let chars_per_second: f32;
-                                                {
-                                                    let mut rng = rand::thread_rng();
-                                                    chars_per_second = rng.gen_range(lower_delay_limit..upper_delay_limit);
-                                                }
-
-                                                let delay = num_of_chars as f32 / chars_per_second;
-                                                let delay_in_ms = (delay * 100.0) as u64;
-                                                println!("Delaying response by {} ms aka {} chars per second", delay_in_ms, chars_per_second);
-                                                tokio::time::sleep(tokio::time::Duration::from_millis(delay_in_ms)).await;
-                                            }
-                                            sender.send(InterTaskMessageToGUI::HandleLLMResponse { response: resp }).expect("Channel to GUI was closed :(");
-                                        });
-                                    },
-                                    Err(_) => {
-                                        eprintln!("Couldn't contact LLM, timeout exceeded");
-                                    }
+                                if let Err(e) = write_tcp_message(&mut writer, msg).await {
+                                    break Err(e);
                                 }
                             }
                             _ => {
-                                eprintln!("Received unexpected message from GUI: {:?}", msg_from_gui);
+                                error!("Received unexpected message from GUI: {:?}", msg_from_gui);
                             }
                         }
                     }
                     Err(e) => {
-                        eprintln!("Couldn't receive message from GUI: {}", e);
+                        error!("Couldn't receive message from GUI: {}", e);
                         panic!("Channel to GUI was closed :(");
                     }
                 }
             };
-            println!("loop_write returned: {:?}", res);
+            debug!("loop_write returned: {:?}", res);
             res
         }
         tokio::select! {
-            lerror = loop_write(writer, receiver_from_gui, sender_to_gui.clone()) => {
+            lerror = loop_write(writer, receiver_from_gui) => {
                 if let Err(e) = lerror{
-                    eprintln!("Error in writing loop: {}", e);
+                    error!("Error in writing loop: {}", e);
                     sender_to_gui.send(InterTaskMessageToGUI::ConnectionClosedUnexpectedly{error: e}).expect("Channel to GUI was closed :(");
 
                 }
             },
             _ = restart_receiver.recv() => {
-                println!("Restarting network task, cancelling writer task");
+                debug!("Restarting network task, cancelling writer task");
             },
         }
-    });
+    }.instrument(span));
 }
 
-pub fn handle_reader(reader: ReadHalf<TlsStream<TcpStream>>, sender_to_gui: Arc<Sender<InterTaskMessageToGUI>>, mut restart_receiver: tokio::sync::broadcast::Receiver<()>) {
+pub fn handle_reader(reader: ReadHalf<BoxedPeerStream>, sender_to_gui: Arc<Sender<InterTaskMessageToGUI>>, mut restart_receiver: tokio::sync::broadcast::Receiver<()>, span: Span) {
     tokio::spawn(async move {
-        println!("Starting to read from socket");
+        debug!("Starting to read from socket");
 
-        async fn loop_reading(mut reader: ReadHalf<TlsStream<TcpStream>>, sender_to_gui: Arc<Sender<InterTaskMessageToGUI>>) -> Result<(), String> {
+        async fn loop_reading(mut reader: ReadHalf<BoxedPeerStream>, sender_to_gui: Arc<Sender<InterTaskMessageToGUI>>) -> Result<(), String> {
             let res = loop {
-                let len = match timeout(Duration::from_secs(300), reader.read_u64()).await {
-                    Ok(Ok(len)) => len as usize,
-                    Ok(Err(e)) => {
-                        eprintln!("Couldn't read message length: {}", e);
-                        break Err(format!("Couldn't read message length: {}", e));
-                    },
-                    Err(_) => {
-                        eprintln!("Couldn't read message length: Timeout");
-                        break Err("Couldn't read message length: Timeout".to_string());
-                    }
+                let msg = match read_tcp_message(&mut reader, Duration::from_secs(300)).await {
+                    Ok(msg) => msg,
+                    Err(e) => break Err(e),
                 };
 
-                println!("Message length: {}", len);
-
-                let mut buffer = vec![0; len];
-
-                match timeout(Duration::from_secs(300), reader.read_exact(&mut buffer)).await {
-                    Ok(Err(e)) => {
-                        eprintln!("Couldn't read message: {}", e);
-                        break Err(format!("Couldn't read message: {}", e));
-                    },
-                    Err(_) => {
-                        eprintln!("Couldn't read message: Timeout");
-                        break Err("Couldn't read message: Timeout".to_string());
-                    },
-                    _ => {}
-                };
-
-                let msg: TcpMessage = match bincode::decode_from_slice(&buffer, bincode::config::standard()) {
-                    Ok((msg, _)) => msg,
-                    Err(e) => {
-                        eprintln!("Couldn't decode message: {}", e);
-                        break Err(format!("Couldn't decode message: {}", e));
-                    }
-                };
-
-                println!("Received message: {:?}", msg);
+                debug!(target: CHAT, "Received message: {:?}", msg);
 
                 sender_to_gui.send(InterTaskMessageToGUI::MessageReceived { msg }).expect("Channel to GUI was closed :(");
             };
-            println!("loop_reading returned: {:?}", res);
+            debug!("loop_reading returned: {:?}", res);
             res
         }
 
         tokio::select! {
             lerror = loop_reading(reader, sender_to_gui.clone()) => {
                 if let Err(e) = lerror{
-                    eprintln!("Error in reading loop: {}", e);
+                    error!("Error in reading loop: {}", e);
                     sender_to_gui.send(InterTaskMessageToGUI::ConnectionClosedUnexpectedly{error: e}).expect("Channel to GUI was closed :(");
 
                 }
             },
             _ = restart_receiver.recv() => {
-                println!("Restarting network task, cancelling reader task");
+                debug!("Restarting network task, cancelling reader task");
             },
         }
-    });
+    }.instrument(span));
 }
 
-pub async fn talk_to_llm(msg: PlayerMessage, mut history: Vec<LLMMessage>, client: reqwest::Client, settings: Arc<settings::Settings>) -> LLMResponseBundle {
-    history.push(LLMMessage {
-        role: "user".to_string(),
-        content: msg.msg,
-        refusal: None,
-    });
-
-    let request = LLMRequest {
-        model: LLMModel::GPT4o,
-        messages: history.clone(),
+/// Send a length prefixed, bincode encoded message to the opponent
+pub async fn write_tcp_message<W: AsyncWrite + Unpin>(writer: &mut W, msg: TcpMessage) -> Result<(), String> {
+    debug!(target: CHAT, "Sending message: {:?}", msg);
+    let encoded_msg = match bincode::encode_to_vec(msg, bincode::config::standard()) {
+        Ok(msg) => msg,
+        Err(e) => {
+            error!("Couldn't encode message: {}", e);
+            return Err(e.to_string());
+        }
     };
+    let len = encoded_msg.len() as u64;
+
+    debug!("Sending message of length {}", len);
+
+    // Send length via socket
+    match timeout(Duration::from_secs(5), writer.write_u64(len)).await {
+        Ok(Err(e)) => {
+            error!("Couldn't send message length: {}", e);
+            return Err(format!("Couldn't send message length: {}", e));
+        },
+        Err(_) => {
+            error!("Couldn't send message length: Timeout");
+            return Err("Couldn't send message length: Timeout".to_string());
+        },
+        _ => {}
+    }
 
-    println!("Sending request to LLM: {:?}", request);
-    let res = client.post("https://api.openai.com/v1/chat/completions")
-        .header("Authorization", format!("Bearer {}", settings.openai_api_key))
-        .json(&request)
-        .send().await;
-
-    let mut new_msg = None;
-
-    match res {
-        Ok(res) => {
-            let res = res.json::<LLMResponse>().await;
-            match res {
-                Ok(res) => {
-                    println!("Received response from LLM: {:?}", res);
-                    if let Some(res) = res.choices.first() {
-                        if res.finish_reason != String::from("stop") {
-                            eprintln!("LLM didn't finish conversation. This is unexpected!");
-                        }
-                        new_msg = Some(res.message.content.clone());
-                        history.push(res.message.clone())
-                    } else {
-                        eprintln!("LLM didn't return any choices. This is unexpected!");
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Couldn't decode response from LLM: {}", e);
-                }
-            }
+    match timeout(Duration::from_secs(5), writer.write_all(&encoded_msg[..])).await {
+        Ok(Err(e)) => {
+            error!("Couldn't send message: {}", e);
+            return Err(format!("Couldn't send message: {}", e));
+        },
+        Err(_) => {
+            error!("Couldn't send message: Timeout");
+            return Err("Couldn't send message: Timeout".to_string());
+        },
+        _ => {}
+    }
+
+    match timeout(Duration::from_secs(5), writer.flush()).await {
+        Ok(Err(e)) => {
+            error!("Couldn't flush message: {}", e);
+            Err(format!("Couldn't flush message: {}", e))
+        },
+        Err(_) => {
+            error!("Couldn't flush message: Timeout");
+            Err("Couldn't flush message: Timeout".to_string())
+        },
+        _ => Ok(())
+    }
+}
+
+/// Read a length prefixed, bincode encoded message from the opponent
+pub async fn read_tcp_message<R: AsyncRead + Unpin>(reader: &mut R, read_timeout: Duration) -> Result<TcpMessage, String> {
+    let len = match timeout(read_timeout, reader.read_u64()).await {
+        Ok(Ok(len)) => len as usize,
+        Ok(Err(e)) => {
+            error!("Couldn't read message length: {}", e);
+            return Err(format!("Couldn't read message length: {}", e));
+        },
+        Err(_) => {
+            error!("Couldn't read message length: Timeout");
+            return Err("Couldn't read message length: Timeout".to_string());
         }
+    };
+
+    debug!("Message length: {}", len);
+
+    let mut buffer = vec![0; len];
+
+    match timeout(read_timeout, reader.read_exact(&mut buffer)).await {
+        Ok(Err(e)) => {
+            error!("Couldn't read message: {}", e);
+            return Err(format!("Couldn't read message: {}", e));
+        },
+        Err(_) => {
+            error!("Couldn't read message: Timeout");
+            return Err("Couldn't read message: Timeout".to_string());
+        },
+        _ => {}
+    };
+
+    match bincode::decode_from_slice(&buffer, bincode::config::standard()) {
+        Ok((msg, _)) => Ok(msg),
         Err(e) => {
-            eprintln!("Couldn't send request: {}", e);
+            error!("Couldn't decode message: {}", e);
+            Err(format!("Couldn't decode message: {}", e))
         }
     }
-
-    LLMResponseBundle {
-        new_message_from_llm: new_msg,
-        history,
-    }
 }
@@ -0,0 +1,160 @@
+use std::sync::LazyLock;
+use regex::Regex;
+use crate::i18n::Localization;
+use crate::settings::Settings;
+
+/// Prompts of the output filter in the language the bot chats in
+#[derive(Debug, Clone)]
+pub struct FilterPrompts {
+    /// Hint sent to the LLM (but not stored in the history) when regenerating a reply
+    pub regenerate_prompt: String,
+    /// Replies used if the LLM keeps giving itself away or refuses to answer
+    pub fallback_replies: Vec<String>,
+}
+
+impl FilterPrompts {
+    pub fn new(localization: &Localization, language: &str) -> Self {
+        FilterPrompts {
+            regenerate_prompt: localization.regenerate_prompt(language),
+            fallback_replies: localization.fallback_replies(language),
+        }
+    }
+}
+
+/// Result of running an LLM reply through the output filter
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterOutcome {
+    /// The (possibly cleaned up) reply can be sent to the opponent
+    Accepted(String),
+    /// The reply would give the bot away and should be regenerated
+    Rejected(String),
+}
+
+/// Clean up an LLM reply before it reaches the opponent: strip markdown and emoji, reject replies
+/// containing giveaway phrases and truncate overly long ones.
+pub fn filter_reply(reply: &str, settings: &Settings) -> FilterOutcome {
+    let mut text = reply.to_string();
+
+    if settings.output_filter_strip_markdown {
+        text = strip_markdown(&text);
+    }
+    if settings.output_filter_strip_emoji {
+        text = strip_emoji(&text);
+    }
+
+    if let Some(phrase) = find_giveaway_phrase(&text, &settings.output_filter_giveaway_phrases) {
+        return FilterOutcome::Rejected(format!("giveaway phrase \"{}\"", phrase));
+    }
+
+    if settings.output_filter_max_chars > 0 {
+        text = truncate(&text, settings.output_filter_max_chars);
+    }
+
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        return FilterOutcome::Rejected("empty reply".to_string());
+    }
+
+    FilterOutcome::Accepted(text)
+}
+
+static MARKDOWN_LINK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[([^\]]*)\]\([^)]*\)").unwrap());
+static MARKDOWN_HEADING: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?m)^\s{0,3}#{1,6}\s+").unwrap());
+static MARKDOWN_BULLET: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?m)^\s*[-*+]\s+").unwrap());
+static MARKDOWN_EMPHASIS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\*([^*\s][^*]*)\*").unwrap());
+static REPEATED_SPACES: LazyLock<Regex> = LazyLock::new(|| Regex::new(r" {2,}").unwrap());
+
+/// Remove markdown formatting nobody uses in a quick chat
+pub fn strip_markdown(text: &str) -> String {
+    let text = MARKDOWN_LINK.replace_all(text, "$1");
+    let text = MARKDOWN_HEADING.replace_all(&text, "");
+    let text = MARKDOWN_BULLET.replace_all(&text, "");
+    let text = text.replace("**", "").replace("__", "").replace("~~", "").replace('`', "");
+    MARKDOWN_EMPHASIS.replace_all(&text, "$1").to_string()
+}
+
+/// Remove emoji (but keep ASCII smileys)
+pub fn strip_emoji(text: &str) -> String {
+    let stripped: String = text.chars().filter(|c| !is_emoji(*c)).collect();
+    // Emoji are often surrounded by spaces, don't leave doubled ones behind
+    REPEATED_SPACES.replace_all(&stripped, " ").to_string()
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(c as u32,
+        0x1F000..=0x1FAFF // Pictographs, emoticons, transport, flags, ...
+        | 0x2600..=0x27BF // Misc symbols & dingbats
+        | 0x2300..=0x23FF // Misc technical (watch, hourglass, ...)
+        | 0x2B00..=0x2BFF // Arrows & stars
+        | 0xFE00..=0xFE0F // Variation selectors
+        | 0x200D          // Zero width joiner
+        | 0xE0020..=0xE007F // Tags
+    )
+}
+
+fn find_giveaway_phrase<'a>(text: &str, phrases: &'a [String]) -> Option<&'a String> {
+    let lowercase_text = text.to_lowercase();
+    phrases.iter().find(|phrase| !phrase.is_empty() && lowercase_text.contains(&phrase.to_lowercase()))
+}
+
+/// Shorten a reply to at most `max_chars`, preferably at the end of a sentence
+pub fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+
+    let cut: String = text.chars().take(max_chars).collect();
+
+    if let Some(pos) = cut.rfind(['.', '!', '?']) {
+        if pos >= cut.len() / 2 {
+            return cut[..=pos].to_string();
+        }
+    }
+
+    match cut.rfind(char::is_whitespace) {
+        Some(pos) if pos > 0 => cut[..pos].to_string(),
+        _ => cut,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> Settings {
+        Settings {
+            output_filter_strip_markdown: true,
+            output_filter_strip_emoji: true,
+            output_filter_giveaway_phrases: vec!["as an AI".to_string(), String::new()],
+            output_filter_max_chars: 40,
+            ..Settings::default()
+        }
+    }
+
+    #[test]
+    fn cleans_up_accepted_replies() {
+        assert_eq!(filter_reply("**hey** 😀 how are you?", &settings()), FilterOutcome::Accepted("hey how are you?".to_string()));
+        assert_eq!(filter_reply("- see [this](http://example.com) :)", &settings()), FilterOutcome::Accepted("see this :)".to_string()));
+    }
+
+    #[test]
+    fn rejects_giveaways_and_empty_replies() {
+        assert!(matches!(filter_reply("Well, AS AN AI I can't say", &settings()), FilterOutcome::Rejected(_)));
+        assert!(matches!(filter_reply(" 🤖 ", &settings()), FilterOutcome::Rejected(_)));
+    }
+
+    #[test]
+    fn truncates_at_sentence_or_word_end() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("Hello there. How are you doing", 20), "Hello there.");
+        assert_eq!(truncate("one two three four", 10), "one two");
+        assert_eq!(truncate("abcdefghij", 5), "abcde");
+    }
+
+    #[test]
+    fn truncates_on_char_boundaries() {
+        assert_eq!(truncate("äöü äöü äöü", 6), "äöü");
+        assert_eq!(truncate("ääääääää", 3), "äää");
+        assert_eq!(truncate("😀😀😀 😀😀. ok", 9), "😀😀😀 😀😀.");
+    }
+}
@@ -0,0 +1,84 @@
+use rand::seq::SliceRandom;
+use serde::Deserialize;
+use tracing::{warn};
+
+/// A character the bot plays for one round, configured as `[[personas]]` in the settings
+#[derive(Debug, Deserialize, Clone)]
+pub struct Persona {
+    pub name: String,
+    /// Language code the persona chats in. It's only picked for opponents with this language,
+    /// personas without a language are picked for everyone.
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Replaces the initial prompt, `{event_name}` is replaced by the event name
+    pub base_prompt: String,
+    /// Factor for the simulated typing speed, > 1 types faster
+    #[serde(default = "default_typing_speed_multiplier")]
+    pub typing_speed_multiplier: f32,
+    /// Replaces `humanizer_typo_rate` for this persona
+    #[serde(default)]
+    pub typo_rate: Option<f64>,
+    /// Lines the bot opens the conversation with when taking the initiative, instead of asking the LLM
+    #[serde(default)]
+    pub openers: Vec<String>,
+}
+
+fn default_typing_speed_multiplier() -> f32 {
+    1.0
+}
+
+/// Pick the persona for a round with an opponent speaking `language`: the one chosen by the operator
+/// if it exists, otherwise a random one matching the language
+pub fn pick_persona<'a>(personas: &'a [Persona], language: &str, choice: Option<&str>) -> Option<&'a Persona> {
+    if let Some(choice) = choice.filter(|choice| !choice.is_empty()) {
+        match personas.iter().find(|persona| persona.name == choice) {
+            Some(persona) => return Some(persona),
+            None => warn!("Persona {} doesn't exist, picking a random one", choice),
+        }
+    }
+
+    let matching: Vec<&Persona> = personas.iter()
+        .filter(|persona| persona.language.as_deref().is_none_or(|persona_language| persona_language == language))
+        .collect();
+    matching.choose(&mut rand::thread_rng()).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn persona(name: &str, language: Option<&str>) -> Persona {
+        Persona {
+            name: name.to_string(),
+            language: language.map(str::to_string),
+            base_prompt: String::new(),
+            typing_speed_multiplier: 1.0,
+            typo_rate: None,
+            openers: vec![],
+        }
+    }
+
+    #[test]
+    fn operator_choice_wins_over_language() {
+        let personas = [persona("anna", Some("de")), persona("bob", Some("en"))];
+        assert_eq!(pick_persona(&personas, "en", Some("anna")).unwrap().name, "anna");
+    }
+
+    #[test]
+    fn unknown_or_empty_choice_picks_matching_language() {
+        let personas = [persona("anna", Some("de")), persona("bob", Some("en")), persona("any", None)];
+        for choice in [None, Some(""), Some("nobody")] {
+            for _ in 0..20 {
+                let picked = pick_persona(&personas, "en", choice).unwrap();
+                assert!(picked.name == "bob" || picked.name == "any", "{}", picked.name);
+            }
+        }
+    }
+
+    #[test]
+    fn no_matching_persona() {
+        let personas = [persona("anna", Some("de"))];
+        assert!(pick_persona(&personas, "en", None).is_none());
+        assert!(pick_persona(&[], "en", None).is_none());
+    }
+}
@@ -0,0 +1,59 @@
+use std::fs;
+use config::{Config, File};
+use serde::Deserialize;
+use tracing::{warn};
+
+/// A named group of prompt templates, loaded from one file of the prompt library directory
+#[derive(Debug, Deserialize, Clone)]
+pub struct PromptCategory {
+    pub name: String,
+    pub prompts: Vec<PromptTemplate>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PromptTemplate {
+    /// Short label shown on the insert button
+    pub title: String,
+    /// Text inserted into the custom prompt
+    pub text: String,
+}
+
+/// Load all prompt categories (TOML or JSON files) from the given directory, sorted by name
+pub fn load_prompt_library(dir: &str) -> Vec<PromptCategory> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Failed to read prompt library directory: {}", e);
+            return vec![];
+        }
+    };
+
+    let mut categories: Vec<PromptCategory> = vec![];
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.extension().is_some_and(|ext| ext == "toml" || ext == "json") {
+            continue;
+        }
+
+        let category = Config::builder()
+            .add_source(File::from(path.as_path()))
+            .build()
+            .and_then(|c| c.try_deserialize::<PromptCategory>());
+
+        match category {
+            Ok(category) => categories.push(category),
+            Err(e) => {
+                warn!("Failed to load prompt library file {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    categories.sort_by(|a, b| a.name.cmp(&b.name));
+    categories
+}
+
+/// Rough token estimate for a text, assuming ~4 characters per token
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
@@ -1,14 +1,24 @@
 use eframe::egui::{Context, RichText, ScrollArea, TextEdit};
 use eframe::{egui, Frame};
 use egui_extras::{Size, StripBuilder};
-use crate::{ApplicationState, InterTaskMessageToNetworkTask, TcpMessage};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use tracing::{warn};
+use crate::bot::system_prompts;
+use crate::moderation::{check_endpoint, check_locally, layer_custom_prompt, redact};
+use crate::prompt_library::estimate_tokens;
+use crate::settings::{EndpointFailure, ModerationPolicy};
+use crate::{ApplicationState, InterTaskMessageToGUI, InterTaskMessageToNetworkTask, TcpMessage};
+
+/// Time the players have to write the custom prompt, in seconds
+pub const PROMPTING_DURATION_SECS: u64 = 90;
 
 pub fn render_prompting_screen(app: &mut ApplicationState, ctx: &Context, frame: &mut Frame){
     let time_elapsed = app.prompting_start_time.unwrap().elapsed().unwrap().as_secs();
-    let time = if time_elapsed >= 90 {
+    let time = if time_elapsed >= PROMPTING_DURATION_SECS {
         0
     }else{
-        90 - time_elapsed
+        PROMPTING_DURATION_SECS - time_elapsed
     };
 
 
@@ -29,26 +39,49 @@ pub fn render_prompting_screen(app: &mut ApplicationState, ctx: &Context, frame:
                 strip.cell(|ui|{
                     ui.vertical_centered(|ui|{
                         ui.add_space(20.0);
-                        ui.heading("Start Prompting!");
+                        ui.heading(app.text("prompting_heading"));
                         ui.add_space(30.0);
-                        ui.label("Initial Prompt");
-                        ScrollArea::vertical().max_height(400.0).show(ui, |ui|{
+                        render_prompt_library(app, ui);
+                        ui.add_space(10.0);
+                        ui.label(app.text("initial_prompt"));
+                        ScrollArea::vertical().max_height(300.0).show(ui, |ui|{
                             let mut text_edit = TextEdit::multiline(&mut app.custom_prompt);
-                            if app.marked_as_prompt_ready{
+                            if app.marked_as_prompt_ready || app.prompt_moderation_pending{
                                 text_edit = text_edit.interactive(false);
                             }
-                            ui.add_sized([ui.available_width(), 400.0], text_edit);
+                            ui.add_sized([ui.available_width(), 300.0], text_edit);
+                        });
+                        ui.label(RichText::new(app.localization.format(&app.language, "prompt_size", &[
+                            ("chars", app.custom_prompt.chars().count().to_string()),
+                            ("tokens", estimate_tokens(&app.custom_prompt).to_string()),
+                        ])).small());
+                        ui.add_space(5.0);
+                        egui::CollapsingHeader::new(app.text("prompt_preview")).show(ui, |ui|{
+                            ScrollArea::vertical().id_salt("prompt_preview").max_height(150.0).show(ui, |ui|{
+                                let language = app.bot_language();
+                                let custom_prompt = layer_custom_prompt(&app.custom_prompt, &app.localization.custom_prompt_template(&language));
+                                for prompt in system_prompts(&app.localization, &language, app.persona.as_ref(), app.round_difficulty.as_ref(), custom_prompt){
+                                    ui.label(RichText::new("developer:").strong());
+                                    ui.label(prompt);
+                                    ui.add_space(5.0);
+                                }
+                            });
                         });
                         ui.add_space(10.0);
-                        let button = egui::Button::new("Submit Prompt");
 
-                        if app.marked_as_prompt_ready{
+                        if let Some(warning) = &app.prompt_moderation_warning{
+                            ui.label(warning).highlight();
+                            ui.add_space(10.0);
+                        }
+
+                        let button = egui::Button::new(app.text("submit_prompt"));
+
+                        if app.marked_as_prompt_ready || app.prompt_moderation_pending{
                             ui.add_enabled(false, button);
                             ui.spinner();
                         }else{
                             if ui.add(button).clicked(){
-                                app.marked_as_prompt_ready = true;
-                                app.mpsc_sender.send(InterTaskMessageToNetworkTask::SendMsg { msg: TcpMessage::PromptingFinished }).expect("Failed to send message to network task");
+                                submit_custom_prompt(app, false);
                             }
                         }
                     });
@@ -73,4 +106,128 @@ pub fn render_prompting_screen(app: &mut ApplicationState, ctx: &Context, frame:
                 });
             });
     });
-}
\ No newline at end of file
+}
+
+/// Category picker, randomizer and template buttons above the prompt input
+fn render_prompt_library(app: &mut ApplicationState, ui: &mut egui::Ui){
+    if app.prompt_library.is_empty(){
+        return;
+    }
+
+    ui.add_enabled_ui(!app.marked_as_prompt_ready, |ui|{
+        ui.horizontal(|ui|{
+            let selected_name = app.prompt_library.get(app.selected_prompt_category).map(|c| c.name.clone()).unwrap_or_default();
+            egui::ComboBox::from_id_salt("prompt_category").selected_text(selected_name).show_ui(ui, |ui|{
+                for (i, category) in app.prompt_library.iter().enumerate(){
+                    ui.selectable_value(&mut app.selected_prompt_category, i, category.name.as_str());
+                }
+            });
+
+            if ui.button(app.text("random_idea")).clicked(){
+                let mut rng = rand::thread_rng();
+                let category = &app.prompt_library[rng.gen_range(0..app.prompt_library.len())];
+                if let Some(template) = category.prompts.choose(&mut rng){
+                    let text = template.text.clone();
+                    let category_name = category.name.clone();
+                    insert_template(app, &text, category_name);
+                }
+            }
+        });
+
+        if let Some(category) = app.prompt_library.get(app.selected_prompt_category).cloned(){
+            ui.horizontal_wrapped(|ui|{
+                for template in category.prompts.iter(){
+                    if ui.button(format!("+ {}", template.title)).on_hover_text(template.text.as_str()).clicked(){
+                        insert_template(app, &template.text, category.name.clone());
+                    }
+                }
+            });
+        }
+    });
+}
+
+fn insert_template(app: &mut ApplicationState, text: &str, category: String){
+    if !app.custom_prompt.is_empty() && !app.custom_prompt.ends_with('\n'){
+        app.custom_prompt.push('\n');
+    }
+    app.custom_prompt.push_str(text);
+    app.custom_prompt_category = Some(category);
+}
+
+/// Run the moderation on the custom prompt and mark it as ready if it passes.
+/// `forced` is set if the prompting time ran out, flagged prompts are dropped instead of rejected then.
+pub fn submit_custom_prompt(app: &mut ApplicationState, forced: bool){
+    let matches = check_locally(&app.custom_prompt, &app.settings);
+    if !matches.is_empty(){
+        warn!("Custom prompt matched blocked keywords/patterns: {:?}", matches);
+        match app.settings.moderation_policy{
+            ModerationPolicy::Reject if !forced => {
+                app.prompt_moderation_warning = Some(app.text("prompt_not_allowed"));
+                return;
+            },
+            ModerationPolicy::Redact => {
+                app.custom_prompt = redact(&app.custom_prompt, &app.settings);
+            },
+            _ => {
+                app.custom_prompt.clear();
+            }
+        }
+    }
+
+    if app.settings.moderation_endpoint.is_empty() || app.custom_prompt.trim().is_empty(){
+        mark_prompt_as_ready(app);
+        return;
+    }
+
+    app.prompt_moderation_pending = true;
+    let sender = app.mpsc_gui_sender.clone();
+    let check = check_endpoint(app.custom_prompt.clone(), app.reqwest_client.clone(), app.settings.moderation_endpoint.clone(), app.settings.openai_api_key.clone());
+    let on_failure = app.settings.moderation_endpoint_failure;
+    tokio::spawn(async move {
+        let failure = match tokio::time::timeout(std::time::Duration::from_secs(10), check).await{
+            Ok(Ok(flagged_categories)) => {
+                sender.send(InterTaskMessageToGUI::ModerationResult { flagged_categories, forced }).expect("Channel to GUI was closed :(");
+                return;
+            },
+            Ok(Err(e)) => e,
+            Err(_) => "timed out".to_string(),
+        };
+        let result = match on_failure {
+            EndpointFailure::Accept => {
+                warn!("Moderation check failed ({}), accepting the prompt as moderation_endpoint_failure is \"accept\"", failure);
+                InterTaskMessageToGUI::ModerationResult { flagged_categories: vec![], forced }
+            },
+            EndpointFailure::Drop => {
+                warn!("Moderation check failed ({}), dropping the prompt as moderation_endpoint_failure is \"drop\"", failure);
+                // The player can't fix this, so drop the prompt like when the prompting time runs out
+                InterTaskMessageToGUI::ModerationResult { flagged_categories: vec!["moderation_unavailable".to_string()], forced: true }
+            },
+        };
+        sender.send(result).expect("Channel to GUI was closed :(");
+    });
+}
+
+pub fn handle_moderation_result(app: &mut ApplicationState, flagged_categories: Vec<String>, forced: bool){
+    if !app.prompt_moderation_pending{
+        // Round was reset in the meantime
+        return;
+    }
+    app.prompt_moderation_pending = false;
+
+    if !flagged_categories.is_empty(){
+        warn!("Custom prompt flagged by moderation endpoint: {:?}", flagged_categories);
+        if app.settings.moderation_policy == ModerationPolicy::Reject && !forced{
+            app.prompt_moderation_warning = Some(app.localization.format(&app.language, "prompt_flagged", &[("categories", flagged_categories.join(", "))]));
+            return;
+        }
+        app.custom_prompt.clear();
+    }
+
+    mark_prompt_as_ready(app);
+}
+
+fn mark_prompt_as_ready(app: &mut ApplicationState){
+    app.prompt_moderation_warning = None;
+    app.marked_as_prompt_ready = true;
+    app.mpsc_sender.send(InterTaskMessageToNetworkTask::SendMsg { msg: TcpMessage::PromptingFinished }).expect("Failed to send message to network task");
+}
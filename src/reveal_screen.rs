@@ -0,0 +1,144 @@
+use eframe::egui::{Align, Color32, Context, Margin, RichText, ScrollArea, Separator};
+use eframe::{egui, Frame};
+use egui_extras::{Size, StripBuilder};
+use crate::{finish_round, ApplicationState, ChatMessage, ChatMessageOrigin};
+
+/// Shows both chats again, labeled as human and AI, with the opponent's custom prompt and
+/// details about the replies of the opponent's bot
+pub fn render_reveal_screen(app: &mut ApplicationState, ctx: &Context, _frame: &mut Frame){
+    render_continue_panel(app, ctx);
+
+    egui::CentralPanel::default().show(ctx, |ui| {
+        let total_width = ui.available_width();
+        let content_width = total_width*0.9;
+        let side_width = (total_width - content_width)/2.0;
+
+        StripBuilder::new(ui)
+            .size(Size::exact(side_width))
+            .size(Size::exact(content_width))
+            .size(Size::exact(side_width))
+            .horizontal(|mut strip|{
+                strip.cell(|_ui|{
+
+                });
+                strip.cell(|ui|{
+                    ui.vertical_centered(|ui|{
+                        ui.add_space(10.0);
+                        let text = if app.correctly_guessed.unwrap_or(false){
+                            app.text("guessed_correctly")
+                        }else{
+                            app.text("guessed_incorrectly")
+                        };
+                        ui.label(RichText::from(text).size(30.0));
+                        ui.add_space(10.0);
+                        StripBuilder::new(ui)
+                            .size(Size::exact((content_width/2.0)-15.0))
+                            .size(Size::exact(10.0))
+                            .size(Size::exact((content_width/2.0)-15.0))
+                            .horizontal(|mut strip|{
+                                strip.cell(|ui|{
+                                    show_chat(app, ui, 0);
+                                });
+                                strip.cell(|ui|{
+                                    ui.add(Separator::default().vertical());
+                                });
+                                strip.cell(|ui|{
+                                    show_chat(app, ui, 1);
+                                });
+                            });
+                    });
+                });
+                strip.cell(|_ui|{
+
+                });
+            });
+    });
+}
+
+/// One chat with its label, the AI chat also shows the persona and the reply details
+fn show_chat(app: &ApplicationState, ui: &mut egui::Ui, chat: u8){
+    let (name, history) = if chat == 0 {
+        (app.text("chat_1"), &app.chat1_history)
+    } else {
+        (app.text("chat_2"), &app.chat2_history)
+    };
+    let is_human = chat == app.human_chat;
+
+    let mut frame = egui::Frame::default();
+    let fill = if is_human { "#1A4D33" } else { "#661A33" };
+    frame = frame.inner_margin(Margin::same(5.0)).fill(Color32::from_hex(fill).unwrap());
+    frame.show(ui, |ui|{
+        let label = if is_human { app.text("reveal_human") } else { app.text("reveal_ai") };
+        ui.label(RichText::new(format!("{} - {}", name, label)).size(24.0).strong());
+        if !is_human {
+            if let Some(persona) = &app.opponent_persona {
+                ui.label(app.localization.format(&app.language, "reveal_persona", &[("persona", persona.clone())]));
+            }
+        }
+        ScrollArea::vertical().id_salt(("reveal_chat", chat)).stick_to_bottom(true).max_height(ui.available_height()).show(ui, |ui|{
+            let max_width = ui.available_width();
+            ui.set_width(max_width);
+            show_annotated_messages(app, ui, history, max_width);
+        });
+    });
+}
+
+fn show_annotated_messages(app: &ApplicationState, ui: &mut egui::Ui, msgs: &[ChatMessage], max_width: f32){
+    for msg in msgs.iter(){
+        match msg.from{
+            ChatMessageOrigin::Own => {
+                ui.allocate_ui_with_layout([max_width*0.8, 10.0].into(), egui::Layout::right_to_left(Align::Min), |ui|{
+                    crate::game_screen::show_message_frame(ui, msg)
+                });
+            },
+            ChatMessageOrigin::Foreign => {
+                ui.allocate_ui_with_layout([max_width*0.8, 10.0].into(), egui::Layout::left_to_right(Align::Min), |ui|{
+                    crate::game_screen::show_message_frame(ui, msg)
+                });
+                if let Some(annotation) = &msg.annotation {
+                    let mut notes = vec![];
+                    if let Some(latency) = annotation.latency {
+                        notes.push(app.localization.format(&app.language, "reveal_latency", &[("secs", format!("{:.1}", latency.as_secs_f32()))]));
+                    }
+                    if annotation.regenerations > 0 {
+                        notes.push(app.localization.format(&app.language, "reveal_regenerated", &[("count", annotation.regenerations.to_string())]));
+                    }
+                    if annotation.filtered {
+                        notes.push(app.text("reveal_filtered"));
+                    }
+                    if !notes.is_empty() {
+                        ui.label(RichText::new(notes.join(" · ")).small().italics().color(Color32::LIGHT_GRAY));
+                    }
+                }
+            },
+        };
+    }
+}
+
+/// The opponent's custom prompt and the continue button
+fn render_continue_panel(app: &mut ApplicationState, ctx: &Context){
+    egui::TopBottomPanel::bottom("reveal_panel").show(ctx, |ui|{
+        ui.vertical_centered(|ui|{
+            ui.add_space(10.0);
+            ui.label(RichText::new(app.text("reveal_custom_prompt")).strong());
+            match app.opponent_custom_prompt.as_deref().filter(|prompt| !prompt.is_empty()) {
+                Some(prompt) => ui.label(RichText::new(prompt).italics()),
+                None => ui.label(app.text("reveal_no_custom_prompt")),
+            };
+            ui.add_space(10.0);
+
+            let label = if app.settings.reveal_display_secs > 0 {
+                ctx.request_repaint_after(std::time::Duration::from_secs(1));
+                let shown_secs = app.showing_end_screen_since.and_then(|since| since.elapsed().ok()).map_or(0, |shown| shown.as_secs());
+                let remaining = app.settings.reveal_display_secs.saturating_sub(shown_secs);
+                app.localization.format(&app.language, "reveal_continue_in", &[("secs", remaining.to_string())])
+            } else {
+                app.text("reveal_continue")
+            };
+            if ui.button(label).clicked(){
+                finish_round(app);
+            }
+            ui.add_space(10.0);
+        });
+    });
+}
@@ -0,0 +1,217 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use tracing::{warn};
+
+/// Outcome of one round as seen from this booth, stored as one JSON line in `rounds_file`
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RoundRecord {
+    pub id: String,
+    /// Start of the chat phase, in seconds since the unix epoch
+    pub started_at: u64,
+    /// Name the player entered on the welcome screen, shown on the leaderboard
+    #[serde(default)]
+    pub player_name: Option<String>,
+    /// Language the player picked
+    pub language: String,
+    /// Persona of our bot, which the opponent chatted with
+    pub persona: Option<String>,
+    /// Persona of the opponent's bot, which the player chatted with
+    pub opponent_persona: Option<String>,
+    pub difficulty: Option<String>,
+    /// Model of the bots this round
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Custom prompt which steered the opponent's bot
+    #[serde(default)]
+    pub opponent_custom_prompt: Option<String>,
+    /// Prompt library category the opponent's custom prompt is based on
+    #[serde(default)]
+    pub opponent_prompt_category: Option<String>,
+    /// Messages in the chat with the human opponent, both directions
+    #[serde(default)]
+    pub human_chat_messages: usize,
+    /// Messages in the chat with the opponent's bot, both directions
+    #[serde(default)]
+    pub ai_chat_messages: usize,
+    /// Whether the player identified the AI chat, `None` if the round was aborted before the guess
+    pub correctly_guessed: Option<bool>,
+    /// How sure the player was about the guess, in percent
+    #[serde(default)]
+    pub confidence: Option<u8>,
+    /// What gave the AI away, according to the player
+    #[serde(default)]
+    pub reasoning: Option<String>,
+}
+
+impl RoundRecord {
+    /// Empty record of a new round, the outcome is filled in by the caller
+    pub fn new(started_at: SystemTime) -> Self {
+        RoundRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            started_at: started_at.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            ..Default::default()
+        }
+    }
+}
+
+/// Append a round to the records file
+pub fn save_round(path: &str, record: &RoundRecord) {
+    let line = match serde_json::to_string(record) {
+        Ok(line) => line,
+        Err(e) => {
+            warn!("Failed to serialize round record: {}", e);
+            return;
+        }
+    };
+
+    let file = OpenOptions::new().create(true).append(true).open(path);
+    match file {
+        Ok(mut file) => {
+            if let Err(e) = writeln!(file, "{}", line) {
+                warn!("Failed to write round record: {}", e);
+            }
+        },
+        Err(e) => {
+            warn!("Failed to open rounds file: {}", e);
+        }
+    }
+}
+
+/// Load all round records, skipping lines which can't be parsed
+pub fn load_rounds(path: &str) -> Vec<RoundRecord> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => {
+            warn!("Failed to open rounds file: {}", e);
+            return vec![];
+        }
+    };
+
+    BufReader::new(file).lines()
+        .map_while(Result::ok)
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str::<RoundRecord>(&line) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                warn!("Skipping invalid round record: {}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Aggregated outcome of several rounds
+#[derive(Debug, Clone, Default)]
+pub struct RoundStats {
+    pub rounds: usize,
+    /// Rounds in which the player made a guess
+    pub guessed: usize,
+    /// Rounds in which the player identified the AI chat
+    pub correct: usize,
+    /// Sum of the confidence of all correct guesses with a confidence
+    confidence_correct_sum: u64,
+    confidence_correct_count: usize,
+    /// Sum of the confidence of all wrong guesses with a confidence
+    confidence_wrong_sum: u64,
+    confidence_wrong_count: usize,
+    /// Guesses with a reasoning
+    pub reasonings: usize,
+}
+
+impl RoundStats {
+    pub fn from_records<'a>(records: impl IntoIterator<Item = &'a RoundRecord>) -> Self {
+        let mut stats = RoundStats::default();
+        for record in records {
+            stats.rounds += 1;
+            if let Some(correct) = record.correctly_guessed {
+                stats.guessed += 1;
+                if correct {
+                    stats.correct += 1;
+                }
+                if let Some(confidence) = record.confidence {
+                    if correct {
+                        stats.confidence_correct_sum += confidence as u64;
+                        stats.confidence_correct_count += 1;
+                    } else {
+                        stats.confidence_wrong_sum += confidence as u64;
+                        stats.confidence_wrong_count += 1;
+                    }
+                }
+                if record.reasoning.is_some() {
+                    stats.reasonings += 1;
+                }
+            }
+        }
+        stats
+    }
+
+    /// Share of guesses which identified the AI chat
+    pub fn detection_rate(&self) -> Option<f64> {
+        if self.guessed == 0 {
+            return None;
+        }
+        Some(self.correct as f64 / self.guessed as f64)
+    }
+
+    /// Average confidence of all guesses, in percent
+    pub fn average_confidence(&self) -> Option<f64> {
+        average(self.confidence_correct_sum + self.confidence_wrong_sum, self.confidence_correct_count + self.confidence_wrong_count)
+    }
+
+    /// Average confidence of the correct guesses, in percent
+    pub fn average_confidence_correct(&self) -> Option<f64> {
+        average(self.confidence_correct_sum, self.confidence_correct_count)
+    }
+
+    /// Average confidence of the wrong guesses, in percent
+    pub fn average_confidence_wrong(&self) -> Option<f64> {
+        average(self.confidence_wrong_sum, self.confidence_wrong_count)
+    }
+}
+
+fn average(sum: u64, count: usize) -> Option<f64> {
+    if count == 0 {
+        return None;
+    }
+    Some(sum as f64 / count as f64)
+}
+
+/// Stats of all rounds started today (UTC)
+pub fn today_stats(path: &str) -> RoundStats {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let start_of_day = now - now % 86400;
+    let records = load_rounds(path);
+    RoundStats::from_records(records.iter().filter(|record| record.started_at >= start_of_day))
+}
+
+/// Place on the leaderboard
+#[derive(Debug, Clone)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub stats: RoundStats,
+}
+
+/// Players with the most identified AI chats, ties are broken by the detection rate.
+/// Rounds without a player name are left out.
+pub fn leaderboard(records: &[RoundRecord], limit: usize) -> Vec<LeaderboardEntry> {
+    let mut names: Vec<&str> = records.iter()
+        .filter_map(|record| record.player_name.as_deref())
+        .collect();
+    names.sort();
+    names.dedup();
+
+    let mut entries: Vec<LeaderboardEntry> = names.into_iter()
+        .map(|name| LeaderboardEntry {
+            name: name.to_string(),
+            stats: RoundStats::from_records(records.iter().filter(|record| record.player_name.as_deref() == Some(name))),
+        })
+        .filter(|entry| entry.stats.correct > 0)
+        .collect();
+
+    entries.sort_by(|a, b| b.stats.correct.cmp(&a.stats.correct)
+        .then(b.stats.detection_rate().unwrap_or(0.0).total_cmp(&a.stats.detection_rate().unwrap_or(0.0))));
+    entries.truncate(limit);
+    entries
+}
@@ -0,0 +1,119 @@
+use std::fmt;
+use std::fs;
+use serde::Deserialize;
+
+/// A secret like the API key. It's never printed, neither by `Debug` nor by `Display`.
+#[derive(Deserialize, Clone, Default, PartialEq)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(secret: String) -> Self {
+        Secret(secret)
+    }
+
+    /// The secret itself, only to be used where it's sent or compared
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            write!(f, "\"\"")
+        } else {
+            write!(f, "[redacted]")
+        }
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Read a secret from a file which only its owner (and group) can read. Surrounding whitespace is removed.
+pub fn read_secret_file(path: &str) -> Result<Secret, String> {
+    check_permissions(path)?;
+    let secret = fs::read_to_string(path).map_err(|e| format!("Couldn't read secret file {}: {}", path, e))?;
+    let secret = secret.trim();
+    if secret.is_empty() {
+        return Err(format!("Secret file {} is empty", path));
+    }
+    Ok(Secret::new(secret.to_string()))
+}
+
+#[cfg(unix)]
+fn check_permissions(path: &str) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = fs::metadata(path).map_err(|e| format!("Couldn't read secret file {}: {}", path, e))?.permissions().mode();
+    if mode & 0o007 != 0 {
+        return Err(format!("Secret file {} is accessible by other users (mode {:o}), restrict it with chmod 600", path, mode & 0o777));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_permissions(_path: &str) -> Result<(), String> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_are_never_printed() {
+        let secret = Secret::new("sk-very-secret".to_string());
+        assert_eq!(format!("{:?}", secret), "[redacted]");
+        assert_eq!(format!("{}", secret), "[redacted]");
+        assert_eq!(format!("{:?}", Secret::default()), "\"\"");
+        assert_eq!(secret.expose(), "sk-very-secret");
+    }
+
+    #[cfg(unix)]
+    fn secret_file(name: &str, content: &str, mode: u32) -> String {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("turing-challenge-secret-{}-{}", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn private_files_are_read() {
+        for (name, mode) in [("owner", 0o600), ("group", 0o640)] {
+            let path = secret_file(name, " sk-key\n", mode);
+            assert_eq!(read_secret_file(&path).unwrap().expose(), "sk-key");
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn files_readable_by_others_are_rejected() {
+        for (name, mode) in [("world-readable", 0o644), ("other-only", 0o604), ("world-executable", 0o601)] {
+            let path = secret_file(name, "sk-key", mode);
+            assert!(read_secret_file(&path).is_err(), "{:o}", mode);
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn empty_and_missing_files_are_rejected() {
+        let path = secret_file("empty", " \n", 0o600);
+        assert!(read_secret_file(&path).is_err());
+        fs::remove_file(&path).unwrap();
+        assert!(read_secret_file(&path).is_err());
+    }
+}
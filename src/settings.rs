@@ -1,43 +1,300 @@
 use std::env;
+use std::path::Path;
 use config::{Config, ConfigError, Environment, File};
 use serde::Deserialize;
+use crate::difficulty::Difficulty;
+use crate::logging::LogRotation;
+use crate::moderation::BlockedPatterns;
+use crate::persona::Persona;
+use crate::secret::{read_secret_file, Secret};
+use crate::LLMModel;
+
+/// How the two booths talk to each other
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    /// TLS 1.3 with mutual certificate authentication
+    #[default]
+    Mtls,
+    /// Unencrypted, unauthenticated TCP. Only meant for local development!
+    Plain,
+}
+
+/// How the booth is run
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    /// One player per process, talking to another booth over the network
+    #[default]
+    Networked,
+    /// Both players in one process with a window each, connected via loopback
+    HotSeat,
+    /// A single player against a simulated opponent booth, see [`SoloOpponent`]
+    Solo,
+}
+
+/// Who plays the human side of the simulated opponent in solo mode
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SoloOpponent {
+    /// A second LLM persona pretending to be a human
+    #[default]
+    Llm,
+    /// Messages replayed from a recorded transcript of a real human player
+    Transcript,
+}
+
+/// What happens to a custom prompt flagged by the moderation
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ModerationPolicy {
+    /// The player has to change the prompt. If the prompting time runs out, the prompt is dropped.
+    #[default]
+    Reject,
+    /// Matches of the local lists are removed, prompts flagged by the endpoint are dropped
+    Redact,
+    /// The prompt is silently dropped, the LLM only gets the initial prompt
+    Drop,
+}
+
+/// What happens to a custom prompt if the moderation endpoint can't be reached
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EndpointFailure {
+    /// Fail open: the prompt is accepted if it passed the local lists
+    #[default]
+    Accept,
+    /// Fail closed: the prompt is dropped, the LLM only gets the initial prompt
+    Drop,
+}
 
 #[derive(Debug, Deserialize, Clone, Default)]
 #[allow(unused)]
 pub struct Settings {
+    /// how the booth is run
+    #[serde(default)]
+    pub mode: Mode,
     /// hostname of this rendering server
     pub bind_to_host: String,
     /// port to listen on
     pub port: usize,
     /// hoststring to connect to
     pub connect_to_host: String,
+    /// transport used for the connection between the booths
+    #[serde(default)]
+    pub transport: Transport,
     /// path to the root certificate
     pub root_ca: String,
     /// path to the client certificate
     pub client_cert: String,
     /// path to the client key
     pub client_key: String,
-    /// OpenAI API key
-    pub openai_api_key: String,
-    /// Initial prompt for the LLM
+    /// OpenAI API key, better kept in `openai_api_key_file` or the `OPENAI_API_KEY` environment variable
+    #[serde(default)]
+    pub openai_api_key: Secret,
+    /// File containing the OpenAI API key, which must not be readable by other users. Takes
+    /// precedence over `openai_api_key` and `OPENAI_API_KEY`.
+    #[serde(default)]
+    pub openai_api_key_file: String,
+    /// Model the bot uses unless the difficulty picks another one
+    #[serde(default)]
+    pub llm_model: LLMModel,
+    /// Initial prompt for the LLM, if the catalog of the opponent's language has none
     pub initial_prompt: String,
-    /// Lower bound for llm_take_iniative_after
-    pub llm_take_initiative_after_lower_bound: usize,
-    /// Upper bound for llm_take_iniative_after
-    pub llm_take_initiative_after_upper_bound: usize,
+    /// Directory containing the i18n catalogs, one file per language (e.g. `de.toml`)
+    pub i18n_dir: String,
+    /// Language code used until a player picks another one and for missing strings
+    pub default_language: String,
+    /// Name of the event, replaces `{event_name}` in strings and prompts
+    pub event_name: String,
+    /// Support contact shown on the start screen, replaces `{support_contact}`
+    pub support_contact: String,
+    /// Template the custom prompt is embedded in, `{custom_prompt}` is replaced by the prompt, if the catalog has none
+    pub custom_prompt_template: String,
+    /// Custom prompts containing one of these keywords are flagged (case insensitive)
+    pub moderation_blocked_keywords: Vec<String>,
+    /// Custom prompts matching one of these regular expressions are flagged (case insensitive)
+    pub moderation_blocked_patterns: Vec<String>,
+    /// Optional moderation endpoint (OpenAI moderation API format), empty to disable
+    pub moderation_endpoint: String,
+    /// What to do with flagged custom prompts
+    #[serde(default)]
+    pub moderation_policy: ModerationPolicy,
+    /// What to do with custom prompts if the moderation endpoint fails or times out
+    #[serde(default)]
+    pub moderation_endpoint_failure: EndpointFailure,
+    /// `moderation_blocked_keywords` and `moderation_blocked_patterns`, compiled when loading
+    #[serde(skip)]
+    pub moderation_blocked: BlockedPatterns,
+    /// Time in ms to wait for further messages of the opponent before asking the LLM
+    pub llm_debounce_ms: u64,
+    /// Token budget for the conversation sent to the LLM, older turns are summarized. 0 for no limit
+    pub llm_history_token_budget: usize,
+    /// Remove markdown formatting from LLM replies
+    pub output_filter_strip_markdown: bool,
+    /// Remove emoji from LLM replies
+    pub output_filter_strip_emoji: bool,
+    /// Maximum length of an LLM reply in characters, 0 for no limit
+    pub output_filter_max_chars: usize,
+    /// Replies containing one of these phrases are regenerated (case insensitive)
+    pub output_filter_giveaway_phrases: Vec<String>,
+    /// How often a reply is regenerated before falling back to `output_filter_fallback_replies`
+    pub output_filter_max_regenerations: u8,
+    /// Hint sent to the LLM (but not stored in the history) when regenerating a reply, if the catalog has none
+    pub output_filter_regenerate_prompt: String,
+    /// Replies used if the LLM keeps giving itself away or refuses to answer, if the catalog has none. Empty to stay silent
+    pub output_filter_fallback_replies: Vec<String>,
+    /// Seed for the humanizer, leave out for a random one
+    #[serde(default)]
+    pub humanizer_seed: Option<u64>,
+    /// Probability for each word of an LLM reply to get a typo
+    pub humanizer_typo_rate: f64,
+    /// Probability for an LLM reply to be written in lowercase only
+    pub humanizer_lowercase_rate: f64,
+    /// Probability for an LLM reply with several sentences to be sent as several messages
+    pub humanizer_split_rate: f64,
+    /// Probability for a typo to be corrected with a follow-up message ("*word")
+    pub humanizer_correction_rate: f64,
+    /// Directory containing the prompt library (TOML or JSON files, one per category)
+    pub prompt_library_dir: String,
+    /// Earliest time in seconds after game start at which the bot opens the conversation itself
+    pub llm_take_initiative_after_lower_bound: u64,
+    /// Latest time in seconds after game start at which the bot opens the conversation itself
+    pub llm_take_initiative_after_upper_bound: u64,
+    /// Probability for the bot to open the conversation if the opponent stays quiet
+    pub llm_take_initiative_probability: f64,
+    /// Steering prompt asking the LLM to open the conversation, if the catalog has none
+    pub llm_take_initiative_prompt: String,
+    /// Minimum time in seconds without an answer of the opponent before the bot follows up
+    pub llm_no_response_after_lower_bound: u64,
+    /// Maximum time in seconds without an answer of the opponent before the bot follows up
+    pub llm_no_response_after_upper_bound: u64,
+    /// Probability for the bot to follow up if the opponent doesn't answer
+    pub llm_no_response_probability: f64,
+    /// Steering prompt asking the LLM to follow up when the opponent went quiet, if the catalog has none
+    pub llm_no_response_prompt: String,
+    /// Who plays the human side of the opponent in solo mode
+    #[serde(default)]
+    pub solo_opponent: SoloOpponent,
+    /// Prompt for the LLM persona playing the human in solo mode, if the catalog has none
+    pub solo_opponent_prompt: String,
+    /// Directory containing the recorded transcripts
+    pub transcripts_dir: String,
+    /// Whether the messages of the human opponent are recorded after each round, off by default
+    #[serde(default)]
+    pub record_transcripts: bool,
+    /// File the round records are appended to, one JSON object per line
+    pub rounds_file: String,
+    /// File the "Export CSV" button on the stats screen writes to
+    pub stats_export_file: String,
+    /// Name of the persona the bot plays every round, a random one is picked if unset
+    #[serde(default)]
+    pub persona: Option<String>,
+    /// Characters the bot can play, see [`Persona`]
+    #[serde(default)]
+    pub personas: Vec<Persona>,
+    /// Seconds without input on the start screen until the attract mode starts, 0 disables it
+    pub attract_idle_secs: u64,
+    /// Seconds each slide of the attract mode is shown
+    pub attract_slide_secs: u64,
+    /// Seconds without input during a round until the player is asked whether they're still there,
+    /// 0 disables it
+    pub idle_timeout_secs: u64,
+    /// Seconds the player has to answer before the round is abandoned
+    pub idle_countdown_secs: u64,
+    /// PIN for the admin panel (Ctrl+Shift+A), the panel is disabled if empty
+    #[serde(default)]
+    pub admin_pin: Secret,
+    /// Log level or filter directives like `info,turing_challenge::network=debug`, `RUST_LOG` overrides it
+    pub log_level: String,
+    /// Directory for the log files, only stdout is logged to if empty
+    pub log_dir: String,
+    #[serde(default)]
+    pub log_rotation: LogRotation,
+    /// Whether chat messages, prompts and LLM requests are logged (at debug level)
+    #[serde(default)]
+    pub log_chat_content: bool,
+    /// Name of this booth, added as `booth` label to the metrics
+    pub booth_name: String,
+    /// Port of the Prometheus metrics endpoint (`/metrics`), 0 disables it
+    pub metrics_port: u16,
+    /// Host the metrics endpoint binds to, loopback by default as it has no authentication
+    pub metrics_bind_host: String,
+    /// Port of the spectator feed (HTTP, WebSocket on `/events`), 0 disables it
+    pub spectator_port: u16,
+    /// Seconds the events are delayed before they're sent to the spectators
+    pub spectator_delay_secs: u64,
+    /// Show the main window in fullscreen
+    #[serde(default)]
+    pub fullscreen: bool,
+    /// Seconds the reveal screen is shown after the guess, 0 waits for the continue button
+    pub reveal_display_secs: u64,
+    /// Difficulty preselected on the welcome screen
+    pub default_difficulty: String,
+    /// Difficulty presets the players can pick from, see [`Difficulty`]
+    #[serde(default)]
+    pub difficulties: Vec<Difficulty>,
 }
 
 impl Settings{
-    pub fn new() -> Result<Self, ConfigError>{
+    /// Read `config/default`, the `RUN_MODE` and `local` files, then the given config file on top
+    /// of them and finally the `APP_` environment variables
+    pub fn new(config_file: Option<&Path>) -> Result<Self, ConfigError>{
         let run_mode = env::var("RUN_MODE").unwrap_or_else(|_| "development".into());
 
-        let s = Config::builder().add_source(File::with_name("config/default"))
+        let mut builder = Config::builder().add_source(File::with_name("config/default"))
             .add_source( File::with_name(&format!("config/{}", run_mode))
                              .required(false),)
-            .add_source(File::with_name("config/local").required(false))
+            .add_source(File::with_name("config/local").required(false));
+        if let Some(path) = config_file {
+            builder = builder.add_source(File::from(path));
+        }
+
+        let s = builder
             .add_source(Environment::with_prefix("app"))
             .build()?;
 
-        s.try_deserialize()
+        let mut settings: Settings = s.try_deserialize()?;
+        settings.load_secrets().map_err(ConfigError::Message)?;
+        settings.moderation_blocked = BlockedPatterns::compile(&settings.moderation_blocked_keywords, &settings.moderation_blocked_patterns)
+            .map_err(ConfigError::Message)?;
+        Ok(settings)
+    }
+
+    /// Load the API key from `openai_api_key_file` or `OPENAI_API_KEY`, if it's not in the config.
+    /// Refuses key files other users can read.
+    fn load_secrets(&mut self) -> Result<(), String> {
+        if !self.openai_api_key_file.is_empty() {
+            self.openai_api_key = read_secret_file(&self.openai_api_key_file)?;
+        } else if self.openai_api_key.is_empty() {
+            if let Ok(key) = env::var("OPENAI_API_KEY") {
+                self.openai_api_key = Secret::new(key.trim().to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the booths talk via mtls. Solo mode has no opponent booth and needs no certificates.
+    pub fn uses_mtls(&self) -> bool {
+        self.transport == Transport::Mtls && self.mode != Mode::Solo
     }
-}
\ No newline at end of file
+
+    /// Settings for the two sessions of a hot seat game. The first session listens on `port`,
+    /// the second one on `port + 1` and each one connects to the other via loopback.
+    pub fn hot_seat_sessions(&self) -> (Settings, Settings) {
+        let mut player1 = self.clone();
+        player1.connect_to_host = format!("localhost:{}", self.port + 1);
+
+        let mut player2 = self.clone();
+        player2.port = self.port + 1;
+        // Only one of them can serve the spectator feed on the configured port
+        player2.spectator_port = 0;
+        player2.booth_name = format!("{}-player2", self.booth_name);
+        if self.metrics_port != 0 {
+            player2.metrics_port = self.metrics_port + 1;
+        }
+        player2.connect_to_host = format!("localhost:{}", self.port);
+
+        (player1, player2)
+    }
+}
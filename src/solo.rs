@@ -0,0 +1,270 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use rand::Rng;
+use reqwest::Client;
+use tokio::io::DuplexStream;
+use tokio::sync::{mpsc, Mutex};
+use tracing::{error, info, warn};
+use crate::bot::{steering_message, talk_to_llm, typing_delay};
+use crate::i18n::Localization;
+use crate::network::{read_tcp_message, write_tcp_message};
+use crate::output_filter::FilterPrompts;
+use crate::settings::{Settings, SoloOpponent};
+use crate::{calculate_average_chars_per_second_limits, load_previous_human_response_times, transcripts, LLMMessage, LLMModel, PlayerMessage, TcpMessage};
+
+/// Start a simulated opponent booth for solo mode on the given end of an in-memory stream.
+/// It speaks the same protocol as a real booth: its AI chat is backed by the LLM with the regular
+/// initial prompt, its human chat by a second LLM persona or a recorded transcript.
+pub fn spawn_virtual_opponent(stream: DuplexStream, settings: Arc<Settings>) {
+    tokio::spawn(async move {
+        let (mut reader, mut writer) = tokio::io::split(stream);
+        let (outgoing_sender, mut outgoing_receiver) = mpsc::unbounded_channel::<TcpMessage>();
+
+        tokio::spawn(async move {
+            while let Some(msg) = outgoing_receiver.recv().await {
+                if write_tcp_message(&mut writer, msg).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut opponent = VirtualOpponent::new(settings, outgoing_sender);
+
+        loop {
+            match read_tcp_message(&mut reader, Duration::from_secs(300)).await {
+                Ok(msg) => opponent.handle_message(msg).await,
+                Err(e) => {
+                    info!("Virtual opponent stopped: {}", e);
+                    break;
+                }
+            }
+        }
+    });
+}
+
+struct VirtualOpponent {
+    settings: Arc<Settings>,
+    localization: Localization,
+    /// Language of the player, the simulated booth answers in it
+    language: String,
+    client: Client,
+    /// Model of both simulated chats, depends on the difficulty
+    model: LLMModel,
+    outgoing: mpsc::UnboundedSender<TcpMessage>,
+    /// History of the LLM the player tries to unmask
+    bot_history: Arc<Mutex<Vec<LLMMessage>>>,
+    /// History of the LLM persona playing the human, if no transcript is replayed
+    human_history: Arc<Mutex<Vec<LLMMessage>>>,
+    /// Remaining messages of the replayed transcript
+    transcript: Option<Arc<Mutex<std::vec::IntoIter<String>>>>,
+    bot_contacted: Arc<AtomicBool>,
+    human_contacted: Arc<AtomicBool>,
+    lower_delay_limit: f32,
+    upper_delay_limit: f32,
+}
+
+impl VirtualOpponent {
+    fn new(settings: Arc<Settings>, outgoing: mpsc::UnboundedSender<TcpMessage>) -> Self {
+        let transcript = match settings.solo_opponent {
+            SoloOpponent::Llm => None,
+            SoloOpponent::Transcript => {
+                let transcript = transcripts::load_random_transcript(&settings.transcripts_dir);
+                if transcript.is_none() {
+                    warn!("No recorded transcripts found, the virtual opponent uses the LLM persona instead");
+                }
+                transcript.map(|t| Arc::new(Mutex::new(t.into_iter())))
+            }
+        };
+
+        let (lower_delay_limit, upper_delay_limit) = calculate_average_chars_per_second_limits(&load_previous_human_response_times());
+        let localization = Localization::load(&settings);
+        let language = localization.default_language().to_string();
+
+        VirtualOpponent {
+            bot_history: Arc::new(Mutex::new(vec![LLMMessage {
+                role: "developer".to_string(),
+                content: localization.initial_prompt(&language),
+                refusal: None,
+            }])),
+            human_history: Arc::new(Mutex::new(vec![LLMMessage {
+                role: "developer".to_string(),
+                content: localization.solo_opponent_prompt(&language),
+                refusal: None,
+            }])),
+            transcript,
+            model: settings.llm_model,
+            settings,
+            localization,
+            language,
+            client: Client::new(),
+            outgoing,
+            bot_contacted: Arc::new(AtomicBool::new(false)),
+            human_contacted: Arc::new(AtomicBool::new(false)),
+            lower_delay_limit,
+            upper_delay_limit,
+        }
+    }
+
+    async fn handle_message(&mut self, msg: TcpMessage) {
+        match msg {
+            TcpMessage::MarkedAsReady => {
+                let _ = self.outgoing.send(TcpMessage::MarkedAsReady);
+            },
+            TcpMessage::LanguageSelected(language) => {
+                // The simulated opponent speaks the player's language
+                self.bot_history.lock().await[0].content = self.localization.initial_prompt(&language);
+                self.human_history.lock().await[0].content = self.localization.solo_opponent_prompt(&language);
+                self.language = language.clone();
+                let _ = self.outgoing.send(TcpMessage::LanguageSelected(language));
+            },
+            TcpMessage::PromptingFinished => {
+                let _ = self.outgoing.send(TcpMessage::PromptingFinished);
+                self.start_round();
+            },
+            TcpMessage::Message(player_message) => {
+                if player_message.to_ai {
+                    self.bot_contacted.store(true, Ordering::SeqCst);
+                    self.reply(player_message, self.bot_history.clone(), true);
+                } else if !player_message.from_ai {
+                    self.human_contacted.store(true, Ordering::SeqCst);
+                    self.reply_as_human(player_message);
+                }
+                // Messages from the player's own LLM are meant for the (non-existing) opponent
+            },
+            TcpMessage::DifficultySelected(name) => {
+                // The simulated opponent agrees to every difficulty
+                self.model = self.settings.difficulties.iter()
+                    .find(|difficulty| difficulty.name == name)
+                    .and_then(|difficulty| difficulty.model)
+                    .unwrap_or(self.settings.llm_model);
+                let _ = self.outgoing.send(TcpMessage::DifficultySelected(name));
+            },
+            TcpMessage::PersonaSelected(_) | TcpMessage::CustomPrompt { .. } | TcpMessage::Abandoned | TcpMessage::EndGame => {},
+        }
+    }
+
+    /// Let both chats open the conversation if the player stays quiet
+    fn start_round(&self) {
+        let (bot_after, human_after) = {
+            let mut rng = rand::thread_rng();
+            (
+                rng.gen_range(self.settings.llm_take_initiative_after_lower_bound..=self.settings.llm_take_initiative_after_upper_bound),
+                rng.gen_range(5..=20),
+            )
+        };
+
+        let bot_contacted = self.bot_contacted.clone();
+        let take_initiative_prompt = self.localization.take_initiative_prompt(&self.language);
+        let bot = self.responder(self.bot_history.clone(), true);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(bot_after)).await;
+            if !bot_contacted.swap(true, Ordering::SeqCst) {
+                bot.answer(steering_message(&take_initiative_prompt), true).await;
+            }
+        });
+
+        let human_contacted = self.human_contacted.clone();
+        let human = self.responder(self.human_history.clone(), false);
+        let transcript = self.transcript.clone();
+        let take_initiative_prompt = self.localization.take_initiative_prompt(&self.language);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(human_after)).await;
+            if !human_contacted.swap(true, Ordering::SeqCst) {
+                match transcript {
+                    Some(transcript) => human.replay(transcript).await,
+                    None => human.answer(steering_message(&take_initiative_prompt), true).await,
+                }
+            }
+        });
+    }
+
+    fn reply_as_human(&self, msg: PlayerMessage) {
+        match self.transcript.clone() {
+            Some(transcript) => {
+                let human = self.responder(self.human_history.clone(), false);
+                tokio::spawn(async move {
+                    human.replay(transcript).await;
+                });
+            },
+            None => self.reply(msg, self.human_history.clone(), false),
+        }
+    }
+
+    fn reply(&self, msg: PlayerMessage, history: Arc<Mutex<Vec<LLMMessage>>>, from_ai: bool) {
+        let responder = self.responder(history, from_ai);
+        tokio::spawn(async move {
+            responder.answer(msg, false).await;
+        });
+    }
+
+    fn responder(&self, history: Arc<Mutex<Vec<LLMMessage>>>, from_ai: bool) -> Responder {
+        Responder {
+            settings: self.settings.clone(),
+            client: self.client.clone(),
+            model: self.model,
+            outgoing: self.outgoing.clone(),
+            history,
+            from_ai,
+            filter_prompts: FilterPrompts::new(&self.localization, &self.language),
+            lower_delay_limit: self.lower_delay_limit,
+            upper_delay_limit: self.upper_delay_limit,
+        }
+    }
+}
+
+/// Everything needed to answer in one of the two simulated chats from a spawned task
+struct Responder {
+    settings: Arc<Settings>,
+    client: Client,
+    model: LLMModel,
+    outgoing: mpsc::UnboundedSender<TcpMessage>,
+    history: Arc<Mutex<Vec<LLMMessage>>>,
+    from_ai: bool,
+    filter_prompts: FilterPrompts,
+    lower_delay_limit: f32,
+    upper_delay_limit: f32,
+}
+
+impl Responder {
+    /// Ask the LLM for an answer and send it after a realistic typing delay
+    async fn answer(&self, msg: PlayerMessage, steering: bool) {
+        // Holding the lock keeps the answers of one chat in order
+        let mut history = self.history.lock().await;
+
+        let resp = tokio::time::timeout(Duration::from_secs(30), talk_to_llm(msg, history.clone(), self.client.clone(), self.settings.clone(), self.model, steering, self.filter_prompts.clone())).await;
+
+        match resp {
+            Ok(resp) => {
+                *history = resp.history;
+                if let Some(new_msg) = resp.new_message_from_llm {
+                    self.send_delayed(new_msg).await;
+                }
+            },
+            Err(_) => {
+                error!("Virtual opponent couldn't contact LLM, timeout exceeded");
+            }
+        }
+    }
+
+    /// Send the next message of the recorded transcript, if there is one left
+    async fn replay(&self, transcript: Arc<Mutex<std::vec::IntoIter<String>>>) {
+        let mut transcript = transcript.lock().await;
+        if let Some(next_msg) = transcript.next() {
+            self.send_delayed(next_msg).await;
+        }
+    }
+
+    async fn send_delayed(&self, msg: String) {
+        tokio::time::sleep(typing_delay(msg.chars().count(), self.lower_delay_limit, self.upper_delay_limit)).await;
+
+        let _ = self.outgoing.send(TcpMessage::Message(PlayerMessage {
+            msg,
+            from_ai: self.from_ai,
+            to_ai: false,
+            timestamp: SystemTime::now(),
+            regenerations: 0,
+            filtered: false,
+        }));
+    }
+}
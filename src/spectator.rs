@@ -0,0 +1,188 @@
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use regex::Regex;
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+use crate::settings::Settings;
+use crate::{ApplicationState, Screen};
+
+/// E-mail addresses and phone numbers
+static PERSONAL_DATA: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[\w.+-]+@[\w-]+\.[\w.-]+|\+?\d[\d /-]{5,}\d").unwrap());
+
+/// Event of the running round, sent as JSON to every connected spectator
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SpectatorEvent {
+    /// The booth entered another phase: idle, welcome, prompting, chat, guessing or reveal
+    Phase { phase: String },
+    /// Message in one of the two chats. Which one is the AI is only revealed by the result.
+    Message {
+        /// 1 or 2, as labeled on the booth
+        chat: u8,
+        /// Whether the player at this booth wrote it, otherwise their chat partner did
+        from_player: bool,
+        text: String,
+    },
+    Result {
+        /// 1 or 2, as labeled on the booth
+        ai_chat: u8,
+        correctly_guessed: bool,
+    },
+}
+
+/// Start the spectator server on `bind_to_host` if `spectator_port` is set. Events sent to the returned sender
+/// reach the spectators after `spectator_delay_secs`.
+pub fn spawn_spectator_server(settings: Arc<Settings>) -> broadcast::Sender<SpectatorEvent> {
+    let (sender, mut receiver) = broadcast::channel::<SpectatorEvent>(100);
+    if settings.spectator_port == 0 {
+        return sender;
+    }
+
+    let (delayed_sender, _) = broadcast::channel::<SpectatorEvent>(100);
+
+    // Forward the events with a delay, one after another so the order is kept
+    let forward_sender = delayed_sender.clone();
+    let delay = Duration::from_secs(settings.spectator_delay_secs);
+    let (queue_sender, mut queue_receiver) = tokio::sync::mpsc::unbounded_channel::<(Instant, SpectatorEvent)>();
+    tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    if queue_sender.send((Instant::now() + delay, event)).is_err() {
+                        break;
+                    }
+                },
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Spectator feed skipped {} events", skipped);
+                },
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+    tokio::spawn(async move {
+        while let Some((due, event)) = queue_receiver.recv().await {
+            tokio::time::sleep_until(due).await;
+            // No spectators connected is fine
+            let _ = forward_sender.send(event);
+        }
+    });
+
+    let address = format!("{}:{}", settings.bind_to_host, settings.spectator_port);
+    tokio::spawn(async move {
+        let app = Router::new()
+            .route("/", get(|| async { "The Turing Challenge spectator feed, connect to /events via WebSocket" }))
+            .route("/events", get(handle_upgrade))
+            .with_state(delayed_sender);
+
+        let listener = match tokio::net::TcpListener::bind(&address).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Couldn't start spectator server on {}: {}", address, e);
+                return;
+            }
+        };
+        info!("Spectator feed available on ws://{}/events", address);
+        if let Err(e) = axum::serve(listener, app).await {
+            error!("Spectator server stopped: {}", e);
+        }
+    });
+
+    sender
+}
+
+async fn handle_upgrade(ws: WebSocketUpgrade, State(events): State<broadcast::Sender<SpectatorEvent>>) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| send_events(socket, events.subscribe()))
+}
+
+/// Send every event to one spectator until they disconnect. Anything they send is ignored.
+async fn send_events(mut socket: WebSocket, mut events: broadcast::Receiver<SpectatorEvent>) {
+    loop {
+        tokio::select! {
+            event = events.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                let Ok(json) = serde_json::to_string(&event) else {
+                    continue;
+                };
+                if socket.send(Message::Text(json)).await.is_err() {
+                    break;
+                }
+            },
+            msg = socket.recv() => {
+                match msg {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {},
+                }
+            },
+        }
+    }
+}
+
+/// Name of the phase shown to the spectators
+fn phase(screen: &Screen) -> &'static str {
+    match screen {
+        Screen::Start | Screen::Attract | Screen::Stats => "idle",
+        Screen::Welcome => "welcome",
+        Screen::Prompting => "prompting",
+        Screen::Game => "chat",
+        Screen::End => "guessing",
+        Screen::Reveal => "reveal",
+    }
+}
+
+/// Tell the spectators and the metrics if the booth entered another phase since the last call
+pub fn publish_phase(app: &mut ApplicationState) {
+    let phase = phase(&app.screen);
+    if app.spectator_phase != phase {
+        app.spectator_phase = phase;
+        app.metrics.set_phase(phase);
+        let _ = app.spectator_sender.send(SpectatorEvent::Phase { phase: phase.to_string() });
+    }
+}
+
+/// Publish a chat message, e-mail addresses and phone numbers are removed. `chat` is 0 or 1.
+pub fn publish_message(app: &ApplicationState, chat: u8, from_player: bool, text: &str) {
+    let _ = app.spectator_sender.send(SpectatorEvent::Message {
+        chat: chat + 1,
+        from_player,
+        text: scrub_personal_data(text),
+    });
+}
+
+/// Replace e-mail addresses and phone numbers in chat messages shown to the public
+pub fn scrub_personal_data(text: &str) -> String {
+    PERSONAL_DATA.replace_all(text, "***").to_string()
+}
+
+pub fn publish_result(app: &ApplicationState, correctly_guessed: bool) {
+    let _ = app.spectator_sender.send(SpectatorEvent::Result {
+        ai_chat: if app.human_chat == 0 { 2 } else { 1 },
+        correctly_guessed,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scrubs_emails_and_phone_numbers() {
+        assert_eq!(scrub_personal_data("mail me: jane.doe+ccc@example.org!"), "mail me: ***!");
+        assert_eq!(scrub_personal_data("call +49 170 1234567 or 030/123456"), "call *** or ***");
+    }
+
+    #[test]
+    fn keeps_short_numbers() {
+        assert_eq!(scrub_personal_data("I'm 42 and it's 2024"), "I'm 42 and it's 2024");
+    }
+}
@@ -2,6 +2,7 @@ use crate::egui::RichText;
 use eframe::egui::{Context, Label, Vec2};
 use eframe::{egui, Frame};
 use egui_extras::{Size, StripBuilder};
+use crate::settings::Mode;
 use crate::{ApplicationState, InterTaskMessageToNetworkTask};
 
 pub fn render_start_screen(app: &mut ApplicationState, ctx: &Context, frame: &mut Frame){
@@ -21,21 +22,28 @@ pub fn render_start_screen(app: &mut ApplicationState, ctx: &Context, frame: &mu
                strip.cell(|ui|{
                    ui.vertical_centered(|ui|{
                        ui.add_space(10.0);
-                       ui.heading("The Turing Challenge");
-                       ui.add_space(40.0);
+                       ui.heading(app.text("title"));
+                       ui.add_space(20.0);
+                       render_language_picker(app, ui);
+                       ui.add_space(20.0);
                        ui.allocate_ui_with_layout(Vec2::from([content_width, 20.0]), egui::Layout::left_to_right(egui::Align::Min), |ui|{
-                           let mut label = Label::new(RichText::new("You will see two Chats, one belongs to the other human, the other one to an LLM. You will have 3,5 minutes to find out which one is which!\nYou may change the initial prompt of the LLM your opponent will encounter (max 1,5 minutes)."));
+                           let mut label = Label::new(RichText::new(app.start_rules()));
                            label = label.wrap();
                            ui.add(label);
                        });
                        ui.add_space(30.0);
 
+                       if app.settings.mode == Mode::Solo{
+                           ui.label(RichText::new(app.text("solo_notice")));
+                           ui.add_space(10.0);
+                       }
+
                        if let Some(warning) = &app.warning{
                            ui.label(warning).highlight();
                            ui.add_space(10.0);
                        }
 
-                       if ui.button("Start Game").clicked(){
+                       if ui.button(app.text("start_game")).clicked(){
                            app.start_game_pressed = true;
                            app.mpsc_sender.send(InterTaskMessageToNetworkTask::ConnectTo {
                                host_string: app.settings.connect_to_host.clone(),
@@ -47,7 +55,7 @@ pub fn render_start_screen(app: &mut ApplicationState, ctx: &Context, frame: &mu
                        }
 
                        ui.add_space(50.0);
-                       ui.label(RichText::new("Any Issues?\n Call 28000 for Support!"));
+                       ui.label(RichText::new(app.text("support")));
                    });
                });
                strip.cell(|ui|{
@@ -55,4 +63,21 @@ pub fn render_start_screen(app: &mut ApplicationState, ctx: &Context, frame: &mu
                });
             });
     });
-}
\ No newline at end of file
+}
+
+/// Buttons for all languages with a catalog, the picked one is shared with the opponent
+fn render_language_picker(app: &mut ApplicationState, ui: &mut egui::Ui){
+    let languages = app.localization.languages();
+    if languages.len() < 2{
+        return;
+    }
+
+    ui.horizontal(|ui|{
+        ui.label(app.text("language"));
+        for (code, name) in languages{
+            ui.add_enabled_ui(!app.start_game_pressed, |ui|{
+                ui.selectable_value(&mut app.language, code, name);
+            });
+        }
+    });
+}
@@ -0,0 +1,308 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use eframe::egui::{Context, ProgressBar, RichText, ScrollArea};
+use eframe::{egui, Frame};
+use tracing::{error, info};
+use crate::rounds::{load_rounds, RoundRecord, RoundStats};
+use crate::settings::Settings;
+use crate::{load_previous_human_response_times, ApplicationState, Screen};
+
+/// Prompts listed as most fooling
+const FOOLING_PROMPTS: usize = 5;
+/// Typing speeds from this value on (chars per second) share the last bucket
+const MAX_TYPING_SPEED: usize = 15;
+
+/// Statistics over all recorded rounds, shown on the stats screen and printed by the `stats` command
+#[derive(Debug, Default)]
+pub struct Dashboard {
+    pub overall: RoundStats,
+    /// Per day (UTC), oldest first
+    pub by_day: Vec<(String, RoundStats)>,
+    /// Per persona of the bot the player chatted with
+    pub by_persona: Vec<(String, RoundStats)>,
+    pub by_model: Vec<(String, RoundStats)>,
+    /// Per prompt library category of the opponent's custom prompt
+    pub by_prompt_category: Vec<(String, RoundStats)>,
+    pub average_human_chat_messages: Option<f64>,
+    pub average_ai_chat_messages: Option<f64>,
+    /// Number of human responses per typing speed, in steps of one char per second
+    pub typing_speeds: Vec<usize>,
+    /// Custom prompts whose bot was taken for the human most often
+    pub fooling_prompts: Vec<FoolingPrompt>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FoolingPrompt {
+    pub prompt: String,
+    /// Rounds in which the player made a guess
+    pub guessed: usize,
+    /// Rounds in which the player took the bot for the human
+    pub fooled: usize,
+}
+
+impl Dashboard {
+    /// Load the round records and response times and aggregate them
+    pub fn load(settings: &Settings) -> Self {
+        let records = load_rounds(&settings.rounds_file);
+        Dashboard::from_records(&records, &load_previous_human_response_times())
+    }
+
+    /// `response_times` are in chars per millisecond, as stored by the GUI
+    pub fn from_records(records: &[RoundRecord], response_times: &[f32]) -> Self {
+        let mut typing_speeds = vec![0; MAX_TYPING_SPEED + 1];
+        for chars_per_ms in response_times {
+            let chars_per_second = (chars_per_ms * 1000.0).max(0.0) as usize;
+            typing_speeds[chars_per_second.min(MAX_TYPING_SPEED)] += 1;
+        }
+
+        Dashboard {
+            overall: RoundStats::from_records(records),
+            by_day: group_by(records, |record| Some(date(record.started_at))),
+            by_persona: group_by(records, |record| record.opponent_persona.clone()),
+            by_model: group_by(records, |record| record.model.clone()),
+            by_prompt_category: group_by(records, |record| match (&record.opponent_prompt_category, &record.opponent_custom_prompt) {
+                (Some(category), _) => Some(category.clone()),
+                (None, Some(_)) => Some("(own)".to_string()),
+                (None, None) => Some("(none)".to_string()),
+            }),
+            average_human_chat_messages: average(records.iter().map(|record| record.human_chat_messages)),
+            average_ai_chat_messages: average(records.iter().map(|record| record.ai_chat_messages)),
+            typing_speeds,
+            fooling_prompts: fooling_prompts(records),
+        }
+    }
+}
+
+/// Stats per key, sorted by key. Records without a key are left out.
+fn group_by(records: &[RoundRecord], key: impl Fn(&RoundRecord) -> Option<String>) -> Vec<(String, RoundStats)> {
+    let mut groups: BTreeMap<String, Vec<&RoundRecord>> = BTreeMap::new();
+    for record in records {
+        if let Some(key) = key(record) {
+            groups.entry(key).or_default().push(record);
+        }
+    }
+    groups.into_iter()
+        .map(|(key, records)| (key, RoundStats::from_records(records)))
+        .collect()
+}
+
+fn average(values: impl Iterator<Item = usize>) -> Option<f64> {
+    let (sum, count) = values.fold((0, 0), |(sum, count), value| (sum + value, count + 1));
+    if count == 0 {
+        return None;
+    }
+    Some(sum as f64 / count as f64)
+}
+
+fn fooling_prompts(records: &[RoundRecord]) -> Vec<FoolingPrompt> {
+    let mut prompts: BTreeMap<&str, FoolingPrompt> = BTreeMap::new();
+    for record in records {
+        let (Some(prompt), Some(correct)) = (&record.opponent_custom_prompt, record.correctly_guessed) else {
+            continue;
+        };
+        let entry = prompts.entry(prompt.as_str()).or_insert_with(|| FoolingPrompt { prompt: prompt.clone(), guessed: 0, fooled: 0 });
+        entry.guessed += 1;
+        if !correct {
+            entry.fooled += 1;
+        }
+    }
+
+    let mut prompts: Vec<FoolingPrompt> = prompts.into_values().filter(|prompt| prompt.fooled > 0).collect();
+    prompts.sort_by(|a, b| b.fooled.cmp(&a.fooled).then(a.guessed.cmp(&b.guessed)));
+    prompts.truncate(FOOLING_PROMPTS);
+    prompts
+}
+
+/// Date (UTC) of a unix timestamp as YYYY-MM-DD
+fn date(timestamp: u64) -> String {
+    // Civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let days = (timestamp / 86400) as i64 + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn format_rate(stats: &RoundStats) -> String {
+    match stats.detection_rate() {
+        Some(rate) => format!("{:.0} % ({}/{})", rate * 100.0, stats.correct, stats.guessed),
+        None => "-".to_string(),
+    }
+}
+
+fn format_average(value: Option<f64>) -> String {
+    value.map_or("-".to_string(), |value| format!("{:.1}", value))
+}
+
+/// Write all rounds as CSV, one row per round. Player names are left out.
+pub fn export_csv(records: &[RoundRecord], path: &str) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "id,started_at,date,language,persona,opponent_persona,difficulty,model,opponent_prompt_category,opponent_custom_prompt,human_chat_messages,ai_chat_messages,correctly_guessed,confidence,reasoning")?;
+    for record in records {
+        let row = [
+            record.id.clone(),
+            record.started_at.to_string(),
+            date(record.started_at),
+            record.language.clone(),
+            record.persona.clone().unwrap_or_default(),
+            record.opponent_persona.clone().unwrap_or_default(),
+            record.difficulty.clone().unwrap_or_default(),
+            record.model.clone().unwrap_or_default(),
+            record.opponent_prompt_category.clone().unwrap_or_default(),
+            record.opponent_custom_prompt.clone().unwrap_or_default(),
+            record.human_chat_messages.to_string(),
+            record.ai_chat_messages.to_string(),
+            record.correctly_guessed.map_or("".to_string(), |correct| correct.to_string()),
+            record.confidence.map_or("".to_string(), |confidence| confidence.to_string()),
+            record.reasoning.clone().unwrap_or_default(),
+        ];
+        writeln!(writer, "{}", row.iter().map(|field| csv_field(field)).collect::<Vec<String>>().join(","))?;
+    }
+    writer.flush()
+}
+
+/// Quote a field if it contains a separator, quote or line break
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// `stats [--csv <path>]`: print the statistics or export the rounds as CSV
+pub fn run_cli(settings: &Settings, csv: Option<&str>) {
+    let records = load_rounds(&settings.rounds_file);
+
+    if let Some(path) = csv {
+        match export_csv(&records, path) {
+            Ok(_) => println!("Exported {} rounds to {}", records.len(), path),
+            Err(e) => eprintln!("Failed to export rounds: {}", e),
+        }
+        return;
+    }
+
+    let dashboard = Dashboard::from_records(&records, &load_previous_human_response_times());
+    println!("Rounds: {}, guesses: {}, detection rate: {}", dashboard.overall.rounds, dashboard.overall.guessed, format_rate(&dashboard.overall));
+    println!("Average messages per round: human chat {}, AI chat {}", format_average(dashboard.average_human_chat_messages), format_average(dashboard.average_ai_chat_messages));
+    for (title, groups) in [
+        ("Detection rate by day", &dashboard.by_day),
+        ("Detection rate by persona", &dashboard.by_persona),
+        ("Detection rate by model", &dashboard.by_model),
+        ("Detection rate by prompt category", &dashboard.by_prompt_category),
+    ] {
+        println!();
+        println!("{}", title);
+        for (key, stats) in groups {
+            println!("  {:<24} {}", key, format_rate(stats));
+        }
+    }
+    println!();
+    println!("Human typing speed (chars per second)");
+    for (speed, count) in dashboard.typing_speeds.iter().enumerate() {
+        println!("  {:>3}{} {}", speed, if speed == MAX_TYPING_SPEED { "+" } else { " " }, count);
+    }
+    println!();
+    println!("Most fooling prompts");
+    for prompt in &dashboard.fooling_prompts {
+        println!("  {}/{} {}", prompt.fooled, prompt.guessed, prompt.prompt.replace('\n', " "));
+    }
+}
+
+/// Open the stats screen, reached from the admin panel
+pub fn open_stats_screen(app: &mut ApplicationState) {
+    app.stats = Some(Dashboard::load(&app.settings));
+    app.screen = Screen::Stats;
+}
+
+pub fn render_stats_screen(app: &mut ApplicationState, ctx: &Context, _frame: &mut Frame) {
+    egui::CentralPanel::default().show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.heading("Statistics");
+            if ui.button("Back").clicked() {
+                app.stats = None;
+                app.screen = Screen::Start;
+            }
+            if ui.button("Export CSV").clicked() {
+                let records = load_rounds(&app.settings.rounds_file);
+                match export_csv(&records, &app.settings.stats_export_file) {
+                    Ok(_) => info!("Exported {} rounds to {}", records.len(), app.settings.stats_export_file),
+                    Err(e) => error!("Failed to export rounds: {}", e),
+                }
+            }
+        });
+        let Some(dashboard) = &app.stats else {
+            return;
+        };
+        ui.add_space(10.0);
+
+        ScrollArea::vertical().show(ui, |ui| {
+            ui.label(format!("Rounds: {}, guesses: {}, detection rate: {}", dashboard.overall.rounds, dashboard.overall.guessed, format_rate(&dashboard.overall)));
+            ui.label(format!("Average messages per round: human chat {}, AI chat {}", format_average(dashboard.average_human_chat_messages), format_average(dashboard.average_ai_chat_messages)));
+
+            for (title, groups) in [
+                ("Detection rate by day", &dashboard.by_day),
+                ("Detection rate by persona", &dashboard.by_persona),
+                ("Detection rate by model", &dashboard.by_model),
+                ("Detection rate by prompt category", &dashboard.by_prompt_category),
+            ] {
+                ui.add_space(10.0);
+                ui.label(RichText::new(title).strong());
+                egui::Grid::new(title).num_columns(2).show(ui, |ui| {
+                    for (key, stats) in groups {
+                        ui.label(key);
+                        ui.add(ProgressBar::new(stats.detection_rate().unwrap_or(0.0) as f32).desired_width(300.0).text(format_rate(stats)));
+                        ui.end_row();
+                    }
+                });
+            }
+
+            ui.add_space(10.0);
+            ui.label(RichText::new("Human typing speed (chars per second)").strong());
+            let max_count = dashboard.typing_speeds.iter().copied().max().unwrap_or(0).max(1);
+            egui::Grid::new("typing_speeds").num_columns(2).show(ui, |ui| {
+                for (speed, count) in dashboard.typing_speeds.iter().enumerate() {
+                    ui.label(format!("{}{}", speed, if speed == MAX_TYPING_SPEED { "+" } else { "" }));
+                    ui.add(ProgressBar::new(*count as f32 / max_count as f32).desired_width(300.0).text(count.to_string()));
+                    ui.end_row();
+                }
+            });
+
+            ui.add_space(10.0);
+            ui.label(RichText::new("Most fooling prompts").strong());
+            for prompt in &dashboard.fooling_prompts {
+                ui.label(format!("{}/{}: {}", prompt.fooled, prompt.guessed, prompt.prompt));
+            }
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_of_timestamps() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(86399), "1970-01-01");
+        assert_eq!(date(951782400), "2000-02-29");
+        assert_eq!(date(1709251199), "2024-02-29");
+        assert_eq!(date(1735689600), "2025-01-01");
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field(""), "");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("cr\r"), "\"cr\r\"");
+    }
+}
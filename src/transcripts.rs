@@ -0,0 +1,81 @@
+use std::fs;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use rand::seq::SliceRandom;
+use tracing::{warn};
+
+/// Save the messages of a human player as a new transcript, one message per line
+pub fn save_transcript(dir: &str, messages: &[String]) {
+    if messages.is_empty() {
+        return;
+    }
+
+    if let Err(e) = fs::create_dir_all(dir) {
+        warn!("Failed to create transcripts directory: {}", e);
+        return;
+    }
+
+    let path = Path::new(dir).join(format!("{}.txt", uuid::Uuid::new_v4()));
+    let file = match File::create(&path) {
+        Ok(f) => f,
+        Err(e) => {
+            warn!("Failed to create transcript file: {}", e);
+            return;
+        }
+    };
+
+    let mut writer = BufWriter::new(file);
+
+    for msg in messages {
+        // Keep one message per line
+        if let Err(e) = writeln!(writer, "{}", msg.replace(['\r', '\n'], " ")) {
+            warn!("Failed to write transcript line: {}", e);
+        }
+    }
+}
+
+/// Load all recorded transcripts, skipping empty ones
+pub fn load_transcripts(dir: &str) -> Vec<Vec<String>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Failed to read transcripts directory: {}", e);
+            return vec![];
+        }
+    };
+
+    let mut transcripts = vec![];
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "txt") {
+            continue;
+        }
+
+        let file = match File::open(&path) {
+            Ok(f) => f,
+            Err(e) => {
+                warn!("Failed to open transcript {}: {}", path.display(), e);
+                continue;
+            }
+        };
+
+        let messages: Vec<String> = BufReader::new(file).lines()
+            .map_while(Result::ok)
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+
+        if !messages.is_empty() {
+            transcripts.push(messages);
+        }
+    }
+
+    transcripts
+}
+
+/// Pick a random recorded transcript
+pub fn load_random_transcript(dir: &str) -> Option<Vec<String>> {
+    let transcripts = load_transcripts(dir);
+    transcripts.choose(&mut rand::thread_rng()).cloned()
+}
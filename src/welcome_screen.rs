@@ -21,10 +21,10 @@ pub fn render_welcome_screen(app: &mut ApplicationState, ctx: &Context, frame: &
                 strip.cell(|ui|{
                     ui.vertical_centered(|ui|{
                         ui.add_space(20.0);
-                        ui.heading("Welcome to the Turing Challenge");
+                        ui.heading(app.text("welcome_heading"));
                         ui.add_space(30.0);
                         ui.horizontal(|ui|{
-                            ui.label("Username");
+                            ui.label(app.text("username"));
                             let mut text_edit = TextEdit::singleline(&mut app.name);
 
                             if app.marked_as_ready{
@@ -34,7 +34,9 @@ pub fn render_welcome_screen(app: &mut ApplicationState, ctx: &Context, frame: &
                             ui.add_sized(ui.available_size(), text_edit);
                         });
                         ui.add_space(10.0);
-                        let button = egui::Button::new("Mark as Ready");
+                        render_difficulty_picker(app, ui);
+                        ui.add_space(10.0);
+                        let button = egui::Button::new(app.text("mark_as_ready"));
 
                         if app.marked_as_ready{
                             ui.add_enabled(false, button);
@@ -42,6 +44,8 @@ pub fn render_welcome_screen(app: &mut ApplicationState, ctx: &Context, frame: &
                         }else{
                             if ui.add(button).clicked(){
                                 app.marked_as_ready = true;
+                                app.mpsc_sender.send(InterTaskMessageToNetworkTask::SendMsg { msg: TcpMessage::LanguageSelected(app.language.clone()) }).expect("Channel to network task is closed :(");
+                                app.mpsc_sender.send(InterTaskMessageToNetworkTask::SendMsg { msg: TcpMessage::DifficultySelected(app.difficulty.clone()) }).expect("Channel to network task is closed :(");
                                 app.mpsc_sender.send(InterTaskMessageToNetworkTask::SendMsg { msg: TcpMessage::MarkedAsReady }).expect("Channel to network task is closed :(");
                             }
                         }
@@ -52,4 +56,29 @@ pub fn render_welcome_screen(app: &mut ApplicationState, ctx: &Context, frame: &
                 });
             });
     });
-}
\ No newline at end of file
+}
+
+/// Buttons for the difficulty presets. A pick is sent to the opponent right away, so both players
+/// see the same selection. It can't be changed anymore once one of them is ready.
+fn render_difficulty_picker(app: &mut ApplicationState, ui: &mut egui::Ui){
+    if app.settings.difficulties.is_empty(){
+        return;
+    }
+
+    let difficulties: Vec<String> = app.settings.difficulties.iter().map(|difficulty| difficulty.name.clone()).collect();
+    let previous = app.difficulty.clone();
+
+    ui.horizontal(|ui|{
+        ui.label(app.text("difficulty"));
+        ui.add_enabled_ui(!app.marked_as_ready && !app.marked_as_ready_opponent, |ui|{
+            for name in difficulties{
+                let label = app.text(&format!("difficulty_{}", name));
+                ui.selectable_value(&mut app.difficulty, name, label);
+            }
+        });
+    });
+
+    if app.difficulty != previous{
+        app.mpsc_sender.send(InterTaskMessageToNetworkTask::SendMsg { msg: TcpMessage::DifficultySelected(app.difficulty.clone()) }).expect("Channel to network task is closed :(");
+    }
+}